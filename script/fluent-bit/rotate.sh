@@ -0,0 +1,18 @@
+#!/bin/sh
+# Rotates the files written by the file output of Fluent Bit, which opens them again on every
+# flush. Files larger than MAX_SIZE bytes are renamed to `<file>.1`, replacing the previous one.
+# Files not written for RETENTION_DAYS days (e.g. of removed pods) are deleted.
+set -eu
+
+while true; do
+    for file in /logs/*; do
+        case "$file" in
+        *.1) continue ;;
+        esac
+        if [ -f "$file" ] && [ "$(stat -c %s "$file")" -gt "$MAX_SIZE" ]; then
+            mv "$file" "$file.1"
+        fi
+    done
+    find /logs -type f -mtime "+$RETENTION_DAYS" -delete
+    sleep 60
+done
@@ -0,0 +1,64 @@
+#!/usr/bin/env bash
+
+# Names of the authentication sources registered by this script. Used to delete sources removed
+# from the configuration, while sources added using the web interface are kept.
+MANAGED_AUTH_SOURCES="$(dirname "$GITEA_APP_INI")/auth-sources"
+registered=()
+
+# Print the ID of the authentication source named <name>.
+#
+# Usage: auth_source_id <name>
+auth_source_id() {
+	gitea admin auth list --vertical-bars \
+		| awk -F'|' -v name="$1" 'NR > 1 { gsub(/^ +| +$/, "", $1); gsub(/^ +| +$/, "", $2); if ($2 == name) print $1 }'
+}
+
+# Register or update an authentication source.
+#
+# Usage: auth_source <ldap|oauth> <name> [options...]
+auth_source() {
+	local kind="$1"
+	local name="$2"
+	shift 2
+	local id
+	id=$(auth_source_id "$name")
+	if [ -z "$id" ]; then
+		echo "add authentication source: $name"
+		gitea admin auth "add-$kind" --name "$name" "$@"
+	else
+		echo "update authentication source: $name (id: $id)"
+		gitea admin auth "update-$kind" --id "$id" --name "$name" "$@"
+	fi
+	registered+=("$name")
+}
+
+# Delete the managed authentication sources which were not registered by this run. Sources still
+# used by users can not be deleted and remain managed, so they are deleted once unused.
+delete_removed_sources() {
+	local managed=("${registered[@]}")
+	local name id
+	if [ -f "$MANAGED_AUTH_SOURCES" ]; then
+		while IFS= read -r name; do
+			if [ -z "$name" ] || printf '%s\n' "${registered[@]}" | grep -qxF -- "$name"; then
+				continue
+			fi
+			id=$(auth_source_id "$name")
+			if [ -z "$id" ]; then
+				continue
+			fi
+			echo "delete authentication source: $name (id: $id)"
+			if ! gitea admin auth delete --id "$id"; then
+				echo "failed to delete authentication source: $name (still used by users?)"
+				managed+=("$name")
+			fi
+		done < "$MANAGED_AUTH_SOURCES"
+	fi
+	printf '%s\n' "${managed[@]}" > "$MANAGED_AUTH_SOURCES"
+}
+
+if [ -f "$AUTH_SOURCES" ]; then
+	set +x # Do not print credentials
+	source "$AUTH_SOURCES"
+	set -x
+fi
+delete_removed_sources
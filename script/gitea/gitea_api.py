@@ -0,0 +1,43 @@
+# Helpers shared by the scripts using the Gitea API as root user. Mounted next to the scripts.
+
+import base64
+import json
+import os
+import time
+import urllib.error
+import urllib.request
+
+API_URL = os.environ["GITEA_API_URL"].rstrip("/")
+AUTH = base64.b64encode(
+    f"{os.environ['ROOT_USER']}:{os.environ['ROOT_PASSWD']}".encode()
+).decode()
+
+
+def request(method, path, body=None):
+    """Sends a request to the API and returns the decoded response. Returns `None` if a
+    resource requested using `GET` does not exist."""
+    data = json.dumps(body).encode() if body is not None else None
+    req = urllib.request.Request(API_URL + path, data=data, method=method)
+    req.add_header("Authorization", f"Basic {AUTH}")
+    req.add_header("Content-Type", "application/json")
+    try:
+        with urllib.request.urlopen(req) as resp:
+            content = resp.read()
+            return json.loads(content) if content else None
+    except urllib.error.HTTPError as err:
+        if err.code == 404 and method == "GET":
+            return None
+        raise RuntimeError(f"{method} {path}: {err.code} {err.read().decode()}") from err
+
+
+def wait_for_gitea(timeout=300):
+    deadline = time.time() + timeout
+    while True:
+        try:
+            request("GET", "/version")
+            return
+        except (urllib.error.URLError, RuntimeError) as err:
+            if time.time() > deadline:
+                raise RuntimeError("gitea did not become ready") from err
+            print(f"waiting for gitea: {err}")
+            time.sleep(5)
@@ -3,7 +3,35 @@
 echo "running as user: $UID"
 echo "===== Run Migrations ====="
 set -xeo pipefail
-environment-to-ini
+
+# Settings generated by Gitea unless configured using the environment.
+GENERATED_SETTINGS="security.INSTALL_LOCK security.SECRET_KEY security.INTERNAL_TOKEN \
+	oauth2.JWT_SECRET server.LFS_JWT_SECRET"
+
+# Print the value of <key> in <section> of the existing app.ini.
+#
+# Usage: ini_value <section> <key>
+ini_value() {
+	awk -v section="[$1]" -v key="$2" '
+		/^\[/ { current = $0 }
+		current == section && $0 ~ "^" key " *=" { sub(/^[^=]*= */, ""); print; exit }
+	' "$GITEA_APP_INI"
+}
+
+# Regenerate app.ini from the environment, so settings removed from the configuration do not
+# persist. Only generated settings are taken from the existing app.ini.
+if [[ -f "$GITEA_APP_INI" ]]; then
+	for setting in $GENERATED_SETTINGS; do
+		env="GITEA__${setting%%.*}__${setting#*.}"
+		value="$(ini_value "${setting%%.*}" "${setting#*.}")"
+		if [[ -n "$value" && -z "${!env}" ]]; then
+			export "$env=$value"
+		fi
+	done
+fi
+rm -f "/tmp/app.ini"
+environment-to-ini --config "/tmp/app.ini"
+mv -f "/tmp/app.ini" "$GITEA_APP_INI"
 cat "$GITEA_APP_INI"
 gitea migrate
 
@@ -29,4 +57,17 @@ else
 		--password "$ROOT_PASSWD"
 fi
 
+# Configure authentication sources
+source "/usr/sbin/auth.sh"
+
+# Create pod specific configuration, cron tasks are only run by the first replica
+cp "$GITEA_APP_INI" "$POD_APP_INI"
+if [[ "${HOSTNAME##*-}" != "0" ]]; then
+	export "GITEA__cron__ENABLED=false"
+	for task in $CRON_TASKS; do
+		export "GITEA__cron_0X2E_${task}__ENABLED=false"
+	done
+	environment-to-ini --config "$POD_APP_INI"
+fi
+
 echo "DONE"
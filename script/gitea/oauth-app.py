@@ -0,0 +1,36 @@
+#!/usr/bin/env python3
+# Creates or updates the OAuth2 application `$APP_NAME` owned by the Gitea root user and stores
+# its client ID and a freshly generated client secret in `$OUTPUT_DIR`.
+
+import os
+
+from gitea_api import request, wait_for_gitea
+
+APP_NAME = os.environ["APP_NAME"]
+REDIRECT_URI = os.environ["REDIRECT_URI"]
+OUTPUT_DIR = os.environ.get("OUTPUT_DIR", "/oauth")
+
+
+def main():
+    wait_for_gitea(timeout=600)
+    body = {
+        "name": APP_NAME,
+        "redirect_uris": [REDIRECT_URI],
+        "confidential_client": True,
+    }
+    apps = request("GET", "/user/applications/oauth2?limit=50") or []
+    app = next((app for app in apps if app["name"] == APP_NAME), None)
+    if app is None:
+        print(f"create: oauth2 application '{APP_NAME}'")
+        app = request("POST", "/user/applications/oauth2", body)
+    else:
+        # Updating an application generates a new client secret.
+        print(f"update: oauth2 application '{APP_NAME}'")
+        app = request("PATCH", f"/user/applications/oauth2/{app['id']}", body)
+    for key in ["client_id", "client_secret"]:
+        with open(os.path.join(OUTPUT_DIR, key), "w") as file:
+            file.write(app[key])
+
+
+if __name__ == "__main__":
+    main()
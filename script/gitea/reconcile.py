@@ -0,0 +1,242 @@
+#!/usr/bin/env python3
+# Reconciles users, organisations, repositories, teams and mirrors of a Gitea instance with the
+# state declared in `$STATE_FILE`. Existing resources are updated in place and every difference is
+# reported as drift.
+#
+# `$RECONCILE` restricts the reconciled kinds (e.g. `mirrors`), `$ONLY` restricts mirrors to a
+# single `owner/name`. Existing pull mirrors are synced if `$SYNC_MIRRORS` is set.
+
+import json
+import os
+import secrets
+import sys
+import time
+import urllib.parse
+
+from gitea_api import request, wait_for_gitea
+
+STATE_FILE = os.environ.get("STATE_FILE", "/config/state.json")
+CREDENTIALS_FILE = os.environ.get("CREDENTIALS_FILE", "/credentials/credentials.json")
+RECONCILE = os.environ.get("RECONCILE", "users,orgs,repos,teams,mirrors").split(",")
+ONLY = os.environ.get("ONLY")
+SYNC_MIRRORS = bool(os.environ.get("SYNC_MIRRORS"))
+
+drift = []
+
+
+def diff(kind, name, current, desired):
+    """Returns the changed fields of `desired` and records them as drift."""
+    changes = {}
+    for key, value in desired.items():
+        if value is None or current.get(key) == value:
+            continue
+        print(f"drift: {kind} '{name}': {key} is {current.get(key)!r}, expected {value!r}")
+        drift.append(f"{kind}/{name}/{key}")
+        changes[key] = value
+    return changes
+
+
+def reconcile_user(user):
+    name = user["name"]
+    current = request("GET", f"/users/{name}")
+    if current is None:
+        print(f"create: user '{name}'")
+        password = user.get("password")
+        request("POST", "/admin/users", {
+            "username": name,
+            "email": user["email"],
+            "full_name": user.get("full_name") or "",
+            "password": password or secrets.token_urlsafe(24),
+            "must_change_password": password is None,
+        })
+        current = request("GET", f"/users/{name}")
+    desired = {
+        "email": user["email"],
+        "full_name": user.get("full_name"),
+        "is_admin": user.get("admin", False),
+    }
+    changes = diff("user", name, current, desired)
+    if changes:
+        if "is_admin" in changes:
+            changes["admin"] = changes.pop("is_admin")
+        # Gitea requires the authentication source, which must be kept for users signing in
+        # using LDAP or OpenID Connect.
+        source = {
+            "login_name": current.get("login_name") or name,
+            "source_id": current.get("source_id", 0),
+        }
+        request("PATCH", f"/admin/users/{name}", {**source, **changes})
+
+
+def reconcile_org(org):
+    name = org["name"]
+    desired = {
+        "full_name": org.get("full_name"),
+        "description": org.get("description"),
+        "visibility": org.get("visibility"),
+    }
+    current = request("GET", f"/orgs/{name}")
+    if current is None:
+        print(f"create: org '{name}'")
+        body = {key: value for key, value in desired.items() if value is not None}
+        request("POST", "/orgs", {"username": name, **body})
+        return
+    changes = diff("org", name, current, desired)
+    if changes:
+        request("PATCH", f"/orgs/{name}", changes)
+
+
+def reconcile_repo(repo):
+    owner, name = repo["owner"], repo["name"]
+    desired = {
+        "description": repo.get("description"),
+        "private": repo.get("private", False),
+        "default_branch": repo.get("default_branch"),
+    }
+    current = request("GET", f"/repos/{owner}/{name}")
+    if current is None:
+        print(f"create: repo '{owner}/{name}'")
+        body = {key: value for key, value in desired.items() if value is not None}
+        if request("GET", f"/orgs/{owner}") is not None:
+            request("POST", f"/orgs/{owner}/repos", {"name": name, **body})
+        else:
+            request("POST", f"/admin/users/{owner}/repos", {"name": name, **body})
+        return
+    changes = diff("repo", f"{owner}/{name}", current, desired)
+    if changes:
+        request("PATCH", f"/repos/{owner}/{name}", changes)
+
+
+def reconcile_team(team):
+    org, name = team["org"], team["name"]
+    desired = {
+        "description": team.get("description"),
+        "permission": team.get("permission", "read"),
+    }
+    query = urllib.parse.urlencode({"q": name})
+    result = request("GET", f"/orgs/{org}/teams/search?{query}") or {}
+    current = next((t for t in result.get("data", []) if t["name"] == name), None)
+    if current is None:
+        print(f"create: team '{org}/{name}'")
+        current = request("POST", f"/orgs/{org}/teams", {
+            "name": name,
+            "description": desired["description"] or "",
+            "permission": desired["permission"],
+            "units": [
+                "repo.code", "repo.issues", "repo.pulls", "repo.releases",
+                "repo.wiki", "repo.projects", "repo.packages", "repo.actions",
+            ],
+        })
+    else:
+        changes = diff("team", f"{org}/{name}", current, desired)
+        if changes:
+            request("PATCH", f"/teams/{current['id']}", {"name": name, **changes})
+    team_id = current["id"]
+
+    members = {member["login"] for member in request("GET", f"/teams/{team_id}/members") or []}
+    for member in set(team.get("members", [])) - members:
+        print(f"drift: team '{org}/{name}': missing member '{member}'")
+        drift.append(f"team/{org}/{name}/members/{member}")
+        request("PUT", f"/teams/{team_id}/members/{member}")
+    # Members of group mapped teams are added on sign-in, so they are not declared.
+    for member in members - set(team.get("members", [])):
+        if team.get("group_mapped"):
+            continue
+        print(f"drift: team '{org}/{name}': unexpected member '{member}'")
+        drift.append(f"team/{org}/{name}/members/{member}")
+        request("DELETE", f"/teams/{team_id}/members/{member}")
+
+    repos = {repo["name"] for repo in request("GET", f"/teams/{team_id}/repos") or []}
+    for repo in set(team.get("repos", [])) - repos:
+        print(f"drift: team '{org}/{name}': missing repo '{repo}'")
+        drift.append(f"team/{org}/{name}/repos/{repo}")
+        request("PUT", f"/teams/{team_id}/repos/{org}/{repo}")
+    for repo in repos - set(team.get("repos", [])):
+        print(f"drift: team '{org}/{name}': unexpected repo '{repo}'")
+        drift.append(f"team/{org}/{name}/repos/{repo}")
+        request("DELETE", f"/teams/{team_id}/repos/{org}/{repo}")
+
+
+def load_credentials():
+    try:
+        with open(CREDENTIALS_FILE) as file:
+            return json.load(file)
+    except FileNotFoundError:
+        return {}
+
+
+def reconcile_mirror(mirror, credentials):
+    owner, name = mirror["owner"], mirror["name"]
+    full_name = f"{owner}/{name}"
+    current = request("GET", f"/repos/{owner}/{name}")
+    if current is None:
+        print(f"migrate: '{mirror['url']}' to '{full_name}' (mirror: {mirror['mirror']})")
+        started = time.time()
+        auth = {key: value for key, value in credentials.items() if value is not None}
+        body = {
+            "clone_addr": mirror["url"],
+            "repo_owner": owner,
+            "repo_name": name,
+            "service": mirror.get("service", "git"),
+            "mirror": mirror["mirror"],
+            "private": mirror.get("private", False),
+            "description": mirror.get("description") or "",
+            "lfs": mirror.get("lfs", False),
+            "wiki": True,
+            **auth,
+        }
+        if mirror["mirror"] and mirror.get("interval"):
+            body["mirror_interval"] = mirror["interval"]
+        request("POST", "/repos/migrate", body)
+        print(f"migrate: '{full_name}' done after {time.time() - started:.0f}s")
+        return
+    if current.get("mirror") != mirror["mirror"]:
+        # Gitea cannot convert repositories between mirrors and regular repositories.
+        print(f"drift: mirror '{full_name}': mirror is {current.get('mirror')!r}, "
+              f"expected {mirror['mirror']!r} (not corrected)")
+        drift.append(f"mirror/{full_name}/mirror")
+        return
+    if not mirror["mirror"]:
+        return
+    changes = diff("mirror", full_name, current, {"mirror_interval": mirror.get("interval")})
+    if changes:
+        request("PATCH", f"/repos/{owner}/{name}", changes)
+    if SYNC_MIRRORS:
+        print(f"sync: mirror '{full_name}'")
+        request("POST", f"/repos/{owner}/{name}/mirror-sync")
+
+
+def main():
+    with open(STATE_FILE) as file:
+        state = json.load(file)
+    credentials = load_credentials()
+    wait_for_gitea()
+    errors = 0
+    mirrors = [
+        mirror for mirror in state.get("mirrors", [])
+        if ONLY is None or f"{mirror['owner']}/{mirror['name']}" == ONLY
+    ]
+    steps = [
+        ("users", reconcile_user, state.get("users", [])),
+        ("orgs", reconcile_org, state.get("orgs", [])),
+        ("repos", reconcile_repo, state.get("repos", [])),
+        ("teams", reconcile_team, state.get("teams", [])),
+        ("mirrors", lambda mirror: reconcile_mirror(
+            mirror, credentials.get(f"{mirror['owner']}/{mirror['name']}", {})
+        ), mirrors),
+    ]
+    for kind, reconcile, items in steps:
+        if kind not in RECONCILE:
+            continue
+        for item in items:
+            try:
+                reconcile(item)
+            except RuntimeError as err:
+                print(f"error: {err}", file=sys.stderr)
+                errors += 1
+    print(f"corrected {len(drift)} drifted field(s), {errors} error(s)")
+    sys.exit(1 if errors else 0)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,22 @@
+#!/bin/sh
+
+# Fetch a runner registration token from the Gitea API and store it in `$TOKEN_FILE`.
+# Waits until Gitea is available.
+
+if [ -f /data/.runner ]; then
+	echo "runner already registered"
+	exit 0
+fi
+
+auth=$(printf '%s:%s' "$ROOT_USER" "$ROOT_PASSWD" | base64 | tr -d '\n')
+until response=$(wget -q -O - --header "Authorization: Basic $auth" \
+	"$GITEA_API_URL/admin/runners/registration-token"); do
+	echo "waiting for gitea"
+	sleep 5
+done
+
+echo "$response" | sed -n 's/.*"token" *: *"\([^"]*\)".*/\1/p' > "$TOKEN_FILE"
+if [ ! -s "$TOKEN_FILE" ]; then
+	echo "failed to obtain registration token"
+	exit 1
+fi
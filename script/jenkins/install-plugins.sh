@@ -13,10 +13,14 @@ tee "$JENKINS_HOME/plugins.txt" << EOF
 configuration-as-code
 gitea
 blueocean
+mailer
+email-ext
+oic-auth
+role-strategy
+prometheus
 EOF
 
-jenkins-plugin-cli --verbose -f "$JENKINS_HOME/plugins.txt"
 mkdir -p "$JENKINS_HOME/plugins"
-cp /usr/share/jenkins/ref/plugins/* "$JENKINS_HOME/plugins"
+jenkins-plugin-cli --verbose -f "$JENKINS_HOME/plugins.txt" -d "$JENKINS_HOME/plugins"
 
 echo "DONE"
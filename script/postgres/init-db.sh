@@ -0,0 +1,17 @@
+#!/bin/sh
+# Creates the application user `$DB_USER` and database `$DB_NAME` on the primary of a replicated
+# cluster. Updates the password of an existing user.
+
+set -e
+until pg_isready -q; do
+	echo "waiting for primary $PGHOST"
+	sleep 5
+done
+psql -v ON_ERROR_STOP=1 -v user="$DB_USER" -v password="$DB_PASSWORD" -v db="$DB_NAME" << 'EOF'
+SELECT format('CREATE ROLE %I LOGIN', :'user')
+WHERE NOT EXISTS (SELECT FROM pg_roles WHERE rolname = :'user')\gexec
+ALTER ROLE :"user" WITH LOGIN PASSWORD :'password';
+SELECT format('CREATE DATABASE %I OWNER %I', :'db', :'user')
+WHERE NOT EXISTS (SELECT FROM pg_database WHERE datname = :'db')\gexec
+EOF
+echo "DONE"
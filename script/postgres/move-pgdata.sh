@@ -0,0 +1,15 @@
+#!/bin/sh
+# Moves a data directory initialized at the root of the volume into the `pgdata` subdirectory.
+# Postgres refuses to use a data directory accessible by other users, which may be the case for
+# the root of volumes.
+
+set -e
+cd /var/lib/postgresql/data
+if [ -f PG_VERSION ]; then
+	mkdir -p pgdata
+	chmod 700 pgdata
+	# PG_VERSION is moved last, so an interrupted move is resumed on restart.
+	find . -mindepth 1 -maxdepth 1 ! -name pgdata ! -name lost+found ! -name PG_VERSION \
+		-exec mv {} pgdata/ \;
+	mv PG_VERSION pgdata/
+fi
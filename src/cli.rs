@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -10,8 +10,80 @@ pub struct Cli {
 #[derive(Subcommand, Debug)]
 pub enum Command {
     Init,
-    Apply,
+    Apply(ApplyArgs),
     Destroy,
+    /// Report the health of all deployed components.
+    Status(StatusArgs),
+    /// Import the repositories declared in `[[gitea.mirrors]]` now and report the progress.
+    Import(ImportArgs),
+    /// Print the logs of a component (e.g. `gitea`, `giteadb`, `giteacache` or `jenkins`).
+    Logs(LogsArgs),
+    /// Run a command inside a component (e.g. `gitserver exec gitea -- gitea admin user list`).
+    Exec(ExecArgs),
+    /// Manage the Terraform state.
+    #[command(subcommand)]
+    State(StateCommand),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum StateCommand {
+    /// Move the existing state into the backend configured in `[state]`.
+    Migrate,
+}
+
+#[derive(Args, Debug)]
+pub struct ApplyArgs {
+    /// Wait until all workloads are ready and all jobs completed before printing the access
+    /// information.
+    #[arg(long)]
+    pub wait: bool,
+    /// Maximum time to wait for workloads to become ready (in seconds).
+    #[arg(long, default_value_t = 600)]
+    pub timeout: u64,
+}
+
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+    /// Only import the mirror `<owner>/<name>`.
+    #[arg(long)]
+    pub only: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct LogsArgs {
+    /// Name of the component, `app` label or pod name.
+    pub component: String,
+    /// Print the logs of the init containers (e.g. `init-gitea` or `install-plugins`).
+    #[arg(long)]
+    pub init: bool,
+    /// Only print the logs of this container.
+    #[arg(short, long)]
+    pub container: Option<String>,
+    /// Stream new log lines.
+    #[arg(short, long)]
+    pub follow: bool,
+    /// Number of recent lines to print per container.
+    #[arg(long)]
+    pub tail: Option<i64>,
+}
+
+#[derive(Args, Debug)]
+pub struct ExecArgs {
+    /// Name of the component, `app` label or pod name.
+    pub component: String,
+    /// Container to run the command in. Defaults to the main container.
+    #[arg(short, long)]
+    pub container: Option<String>,
+    /// Command to run. Opens a shell if omitted.
+    #[arg(last = true)]
+    pub command: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct StatusArgs {
+    /// Print the status as JSON.
+    #[arg(long)]
+    pub json: bool,
 }
 
 impl Cli {
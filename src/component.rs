@@ -0,0 +1,187 @@
+use std::io::IsTerminal;
+use std::process::Child;
+
+use anyhow::{bail, Context, Result};
+use tf_bindgen::json::Value;
+use tf_bindgen::Stack;
+
+use crate::cli::{ExecArgs, LogsArgs};
+use crate::kubectl::Kubectl;
+use crate::terraform::Outputs;
+
+/// Prints the logs of the containers of `args.component`. Logs of multiple containers are
+/// prefixed with their pod and container name. Returns the exit code of `kubectl`.
+pub fn logs(stack: &Stack, args: &LogsArgs) -> Result<i32> {
+    let pods = resolve(stack, &args.component)?;
+    let targets: Vec<_> = pods
+        .iter()
+        .flat_map(|pod| {
+            containers(pod, args.init)
+                .into_iter()
+                .filter(|container| args.container.as_ref().is_none_or(|c| c == container))
+                .map(move |container| (pod, container))
+        })
+        .collect();
+    if targets.is_empty() {
+        let kind = match args.init {
+            true => "init containers",
+            false => "containers",
+        };
+        bail!("no matching {kind} found for '{}'", args.component)
+    }
+
+    let prefix = targets.len() > 1;
+    let spawn = |(pod, container): &(&Value, String)| {
+        let mut command = kubectl_of(pod).command();
+        command.args(["logs", &name_of(pod), "--container", container]);
+        if args.follow {
+            command.arg("--follow");
+        }
+        if prefix {
+            command.arg("--prefix");
+        }
+        if let Some(tail) = args.tail {
+            command.arg(format!("--tail={tail}"));
+        }
+        command.spawn().context("failed to run kubectl")
+    };
+    // Followed logs are streamed concurrently, since they never end.
+    let mut code = 0;
+    match args.follow {
+        true => {
+            let children = targets.iter().map(spawn).collect::<Result<Vec<_>>>()?;
+            for child in children {
+                code = code.max(wait(child)?);
+            }
+        }
+        false => {
+            for target in &targets {
+                code = code.max(wait(spawn(target)?)?);
+            }
+        }
+    }
+    Ok(code)
+}
+
+/// Runs `args.command` inside the first running pod of `args.component`, or an interactive shell
+/// if no command is given. Returns the exit code of the command.
+pub fn exec(stack: &Stack, args: &ExecArgs) -> Result<i32> {
+    let pods = resolve(stack, &args.component)?;
+    let pod = pods
+        .iter()
+        .find(|pod| pod["status"]["phase"].as_str() == Some("Running"))
+        .with_context(|| format!("no running pod found for '{}'", args.component))?;
+    let mut command = kubectl_of(pod).command();
+    command.args(["exec", "--stdin"]);
+    if std::io::stdin().is_terminal() {
+        command.arg("--tty");
+    }
+    command.arg(name_of(pod));
+    if let Some(container) = &args.container {
+        command.args(["--container", container]);
+    }
+    command.arg("--");
+    match args.command.is_empty() {
+        true => command.arg("sh"),
+        false => command.args(&args.command),
+    };
+    wait(command.spawn().context("failed to run kubectl")?)
+}
+
+/// Returns the wrapper scoped to the namespace of `pod`.
+fn kubectl_of(pod: &Value) -> Kubectl {
+    Kubectl::new(pod["metadata"]["namespace"].as_str().unwrap_or_default())
+}
+
+/// Returns the pods of `component`. Pods are matched by pod name, `app` or `job-name` label
+/// (e.g. `postgres-giteadb-pooler`), then by the `app` label of a Postgres cluster (e.g. `giteadb`
+/// matches `postgres-giteadb`). Otherwise, pods whose `app` label ends with the component name are
+/// returned (e.g. `giteacache` matches `memcached-giteacache`).
+///
+/// The primary of a replicated Postgres cluster is returned first, so [`exec`] prefers it.
+///
+/// # Errors
+///
+/// Will return `Err` if no pods were found or the component name matches multiple `app` labels.
+fn resolve(stack: &Stack, component: &str) -> Result<Vec<Value>> {
+    let outputs = Outputs::read(stack)?;
+    let mut pods = Vec::new();
+    for kubectl in Kubectl::namespaces(&outputs)? {
+        pods.extend(kubectl.get("pods")?);
+    }
+    let label = |pod: &Value, key: &str| {
+        pod["metadata"]["labels"][key]
+            .as_str()
+            .unwrap_or_default()
+            .to_string()
+    };
+    let matching = |matches: &dyn Fn(&Value) -> bool| -> Vec<Value> {
+        pods.iter().filter(|pod| matches(pod)).cloned().collect()
+    };
+
+    let exact = matching(&|pod| {
+        name_of(pod) == component
+            || label(pod, "app") == component
+            || label(pod, "job-name") == component
+    });
+    if !exact.is_empty() {
+        return Ok(exact);
+    }
+    let postgres = format!("postgres-{component}");
+    let mut cluster = matching(&|pod| label(pod, "app") == postgres);
+    if !cluster.is_empty() {
+        // Patroni labels the current primary using `spilo-role`.
+        cluster.sort_by_key(|pod| label(pod, "spilo-role") != "master");
+        return Ok(cluster);
+    }
+
+    let suffix = format!("-{component}");
+    let derived = matching(&|pod| label(pod, "app").ends_with(&suffix));
+    let mut apps: Vec<_> = match derived.is_empty() {
+        true => pods.iter().map(|pod| label(pod, "app")).collect(),
+        false => derived.iter().map(|pod| label(pod, "app")).collect(),
+    };
+    apps.retain(|app| !app.is_empty());
+    apps.sort();
+    apps.dedup();
+    if derived.is_empty() {
+        bail!(
+            "no pods found for component '{component}' (available: {})",
+            apps.join(", ")
+        )
+    }
+    if apps.len() > 1 {
+        bail!(
+            "component '{component}' is ambiguous, use one of: {}",
+            apps.join(", ")
+        )
+    }
+    Ok(derived)
+}
+
+/// Returns the names of the (init) containers of `pod`.
+fn containers(pod: &Value, init: bool) -> Vec<String> {
+    let key = match init {
+        true => "initContainers",
+        false => "containers",
+    };
+    pod["spec"][key]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|container| container["name"].as_str())
+        .map(String::from)
+        .collect()
+}
+
+fn name_of(pod: &Value) -> String {
+    pod["metadata"]["name"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn wait(mut child: Child) -> Result<i32> {
+    let status = child.wait().context("failed to wait for kubectl")?;
+    Ok(status.code().unwrap_or(1))
+}
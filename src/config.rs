@@ -1,13 +1,40 @@
+use std::collections::HashMap;
 use std::path::Path;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use tf_bindgen::json;
 use tf_bindgen::serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::construct::gitea_auth::{self, AuthSource, LdapSource, OidcSource};
+use crate::construct::postgres::PoolMode;
 
 #[derive(Deserialize, Serialize)]
 #[serde(crate = "::tf_bindgen::serde")]
 pub struct Config {
     pub server: Server,
     pub root: Root,
+    #[serde(default)]
+    pub state: State,
+    #[serde(default)]
+    pub gitea: Gitea,
+    pub mail: Option<Mail>,
+    pub sso: Option<Sso>,
+    pub actions: Option<Actions>,
+    #[serde(default)]
+    pub ci: Ci,
+    #[serde(default)]
+    pub cache: Cache,
+    pub storage: Option<Storage>,
+    pub packages: Option<Packages>,
+    pub indexer: Option<Indexer>,
+    pub ha: Option<Ha>,
+    pub database: Option<Database>,
+    pub pooler: Option<Pooler>,
+    pub monitoring: Option<Monitoring>,
+    pub logs: Option<Logs>,
+    #[serde(default)]
+    pub network: Network,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -25,10 +52,1019 @@ pub struct Root {
     pub email: String,
 }
 
+/// Gitea resources reconciled by the bootstrap job.
+#[derive(Deserialize, Serialize, Default)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct Gitea {
+    #[serde(default)]
+    pub users: Vec<GiteaUser>,
+    #[serde(default)]
+    pub orgs: Vec<GiteaOrg>,
+    #[serde(default)]
+    pub repos: Vec<GiteaRepo>,
+    #[serde(default)]
+    pub teams: Vec<GiteaTeam>,
+    #[serde(default)]
+    pub mirrors: Vec<GiteaMirror>,
+    /// Additional `app.ini` settings grouped by section. Not part of the reconciled state, since
+    /// the settings may contain secrets and changing them would run the bootstrap job again.
+    #[serde(default, skip_serializing)]
+    pub app_ini: HashMap<String, HashMap<String, toml::Value>>,
+    #[serde(default, skip_serializing)]
+    pub auth: GiteaAuth,
+}
+
+impl Gitea {
+    /// Returns the credentials of all mirrors as JSON document keyed by `owner/name`. Stored
+    /// separately from the reconciled state.
+    pub fn mirror_credentials(&self) -> String {
+        let credentials: json::Map<_, _> = self
+            .mirrors
+            .iter()
+            .map(|mirror| {
+                let credentials = json::json!({
+                    "auth_username": mirror.username,
+                    "auth_password": mirror.password,
+                    "auth_token": mirror.token
+                });
+                (format!("{}/{}", mirror.owner, mirror.name), credentials)
+            })
+            .collect();
+        json::to_string(&credentials).expect("serializable mirror credentials")
+    }
+
+    /// Returns the additional `app.ini` settings with all values converted to strings.
+    pub fn app_ini(&self) -> HashMap<String, HashMap<String, String>> {
+        self.app_ini
+            .iter()
+            .map(|(section, values)| {
+                let values = values
+                    .iter()
+                    .map(|(key, value)| {
+                        let value = match value {
+                            toml::Value::String(value) => value.clone(),
+                            value => value.to_string(),
+                        };
+                        (key.clone(), value)
+                    })
+                    .collect();
+                (section.clone(), values)
+            })
+            .collect()
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct GiteaUser {
+    pub name: String,
+    pub email: String,
+    pub full_name: Option<String>,
+    /// Initial password of the user. A random password will be generated if not set, which must
+    /// be changed on first login.
+    pub password: Option<String>,
+    #[serde(default)]
+    pub admin: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct GiteaOrg {
+    pub name: String,
+    pub full_name: Option<String>,
+    pub description: Option<String>,
+    /// One of `public`, `limited` or `private`.
+    pub visibility: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct GiteaRepo {
+    /// Name of the user or organisation owning the repository.
+    pub owner: String,
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub private: bool,
+    pub default_branch: Option<String>,
+}
+
+/// Repository imported from another forge or plain git URL using the Gitea migrate API.
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct GiteaMirror {
+    /// Name of the user or organisation owning the imported repository.
+    pub owner: String,
+    pub name: String,
+    /// Clone URL of the source repository.
+    pub url: String,
+    #[serde(default)]
+    pub service: MirrorService,
+    /// Keep the repository in sync with its source. Imports the repository once otherwise.
+    #[serde(default = "default_mirror")]
+    pub mirror: bool,
+    /// Sync interval of a pull mirror, e.g. `8h0m0s`.
+    pub interval: Option<String>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub private: bool,
+    #[serde(default)]
+    pub lfs: bool,
+    #[serde(default, skip_serializing)]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub password: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub token: Option<String>,
+}
+
+/// Forge hosting the source of a mirror. Determines which metadata can be migrated.
+#[derive(Deserialize, Serialize, Default, Clone, Copy)]
+#[serde(crate = "::tf_bindgen::serde")]
+#[serde(rename_all = "lowercase")]
+pub enum MirrorService {
+    #[default]
+    Git,
+    GitHub,
+    GitLab,
+    Gitea,
+}
+
+fn default_mirror() -> bool {
+    true
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct GiteaTeam {
+    pub org: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// One of `read`, `write` or `admin`.
+    #[serde(default = "default_team_permission")]
+    pub permission: String,
+    #[serde(default)]
+    pub members: Vec<String>,
+    /// Repositories of `org` the team has access to.
+    #[serde(default)]
+    pub repos: Vec<String>,
+    /// Set if members are assigned by a group team map of single sign-on. Members not listed in
+    /// `members` are kept in this case.
+    #[serde(default, skip_deserializing)]
+    pub group_mapped: bool,
+}
+
+/// External authentication sources of Gitea.
+#[derive(Deserialize, Serialize, Default)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct GiteaAuth {
+    #[serde(default)]
+    pub ldap: Vec<LdapAuth>,
+    #[serde(default)]
+    pub oidc: Vec<OidcAuth>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct LdapAuth {
+    pub name: String,
+    pub host: String,
+    #[serde(default = "default_ldap_port")]
+    pub port: i64,
+    #[serde(default)]
+    pub security: LdapSecurity,
+    pub bind_dn: String,
+    pub bind_password: String,
+    pub user_search_base: String,
+    pub user_filter: String,
+    pub admin_filter: Option<String>,
+    #[serde(default = "default_ldap_username_attribute")]
+    pub username_attribute: String,
+    pub firstname_attribute: Option<String>,
+    pub surname_attribute: Option<String>,
+    #[serde(default = "default_ldap_email_attribute")]
+    pub email_attribute: String,
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy)]
+#[serde(crate = "::tf_bindgen::serde")]
+#[serde(rename_all = "lowercase")]
+pub enum LdapSecurity {
+    #[default]
+    Unencrypted,
+    Ldaps,
+    StartTls,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct OidcAuth {
+    pub name: String,
+    /// URL of the `.well-known/openid-configuration` document.
+    pub discovery_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    pub group_claim: Option<String>,
+    pub admin_group: Option<String>,
+    /// Maps groups onto teams of organisations (group -> organisation -> teams).
+    #[serde(default)]
+    pub group_team_map: HashMap<String, HashMap<String, Vec<String>>>,
+}
+
+fn group_team_map(map: &HashMap<String, HashMap<String, Vec<String>>>) -> Option<String> {
+    match map.is_empty() {
+        true => None,
+        false => Some(json::to_string(map).expect("serializable group team map")),
+    }
+}
+
+fn default_ldap_port() -> i64 {
+    389
+}
+
+fn default_ldap_username_attribute() -> String {
+    "uid".to_string()
+}
+
+fn default_ldap_email_attribute() -> String {
+    "mail".to_string()
+}
+
+impl GiteaAuth {
+    /// Returns all configured authentication sources.
+    pub fn sources(&self) -> Vec<AuthSource> {
+        let ldap = self.ldap.iter().map(|ldap| {
+            AuthSource::Ldap(LdapSource {
+                name: ldap.name.clone(),
+                host: ldap.host.clone(),
+                port: ldap.port,
+                security: match ldap.security {
+                    LdapSecurity::Unencrypted => gitea_auth::LdapSecurity::Unencrypted,
+                    LdapSecurity::Ldaps => gitea_auth::LdapSecurity::Ldaps,
+                    LdapSecurity::StartTls => gitea_auth::LdapSecurity::StartTls,
+                },
+                bind_dn: ldap.bind_dn.clone(),
+                bind_password: ldap.bind_password.clone(),
+                user_search_base: ldap.user_search_base.clone(),
+                user_filter: ldap.user_filter.clone(),
+                admin_filter: ldap.admin_filter.clone(),
+                username_attribute: ldap.username_attribute.clone(),
+                firstname_attribute: ldap.firstname_attribute.clone(),
+                surname_attribute: ldap.surname_attribute.clone(),
+                email_attribute: ldap.email_attribute.clone(),
+            })
+        });
+        let oidc = self.oidc.iter().map(|oidc| {
+            AuthSource::OpenIdConnect(OidcSource {
+                name: oidc.name.clone(),
+                discovery_url: oidc.discovery_url.clone(),
+                client_id: oidc.client_id.clone(),
+                client_secret: oidc.client_secret.clone(),
+                scopes: oidc.scopes.clone(),
+                group_claim: oidc.group_claim.clone(),
+                admin_group: oidc.admin_group.clone(),
+                group_team_map: group_team_map(&oidc.group_team_map),
+            })
+        });
+        ldap.chain(oidc).collect()
+    }
+}
+
+fn default_team_permission() -> String {
+    "read".to_string()
+}
+
+/// S3-compatible object storage used by Gitea. Uses an in-cluster MinIO server if `minio` is
+/// set, an external bucket otherwise.
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct Storage {
+    #[serde(default)]
+    pub minio: bool,
+    pub endpoint: Option<String>,
+    #[serde(default = "default_storage_bucket")]
+    pub bucket: String,
+    #[serde(default = "default_state_region")]
+    pub region: String,
+    pub access_key: Option<String>,
+    pub secret_key: Option<String>,
+    #[serde(default = "default_storage_use_ssl")]
+    pub use_ssl: bool,
+    /// Data stored inside the bucket.
+    #[serde(default = "default_storage_targets")]
+    pub targets: Vec<StorageTarget>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "::tf_bindgen::serde")]
+#[serde(rename_all = "lowercase")]
+pub enum StorageTarget {
+    /// Default storage of all data not stored inside repositories.
+    Storage,
+    Lfs,
+    Attachment,
+    Packages,
+}
+
+impl StorageTarget {
+    /// Returns the `app.ini` section configuring this target.
+    pub fn section(&self) -> &'static str {
+        match self {
+            StorageTarget::Storage => "storage",
+            StorageTarget::Lfs => "lfs",
+            StorageTarget::Attachment => "attachment",
+            StorageTarget::Packages => "packages",
+        }
+    }
+}
+
+/// Gitea package registry (container images, npm, cargo, maven, ...).
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct Packages {
+    /// Maximum size of an uploaded package or container layer. `0` disables the limit.
+    #[serde(default = "default_packages_max_body_size")]
+    pub max_body_size: String,
+    /// Timeout of uploads and downloads in seconds.
+    #[serde(default = "default_packages_timeout")]
+    pub timeout: i64,
+    /// Separate bucket of the object storage used to store packages.
+    pub bucket: Option<String>,
+}
+
+impl Packages {
+    /// Returns the annotations required by the ingress controller to handle large uploads.
+    pub fn ingress_annotations(&self) -> HashMap<String, String> {
+        let timeout = self.timeout.to_string();
+        [
+            ("proxy-body-size", self.max_body_size.clone()),
+            ("proxy-read-timeout", timeout.clone()),
+            ("proxy-send-timeout", timeout),
+            ("proxy-request-buffering", "off".to_string()),
+        ]
+        .into_iter()
+        .map(|(key, value)| (format!("nginx.ingress.kubernetes.io/{key}"), value))
+        .collect()
+    }
+}
+
+/// Code and issue search of Gitea.
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct Indexer {
+    #[serde(default)]
+    pub kind: IndexerKind,
+    /// URL of an external Elasticsearch cluster. An in-cluster node is deployed if not set.
+    pub url: Option<String>,
+    /// Size of the volume storing the index.
+    #[serde(default = "default_indexer_size")]
+    pub size: String,
+}
+
+/// Search engine used to index code and issues. Gitea's code indexer only supports bleve and
+/// Elasticsearch.
+#[derive(Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "::tf_bindgen::serde")]
+#[serde(rename_all = "lowercase")]
+pub enum IndexerKind {
+    /// Index embedded into Gitea.
+    #[default]
+    Bleve,
+    Elasticsearch,
+}
+
+fn default_indexer_size() -> String {
+    "10Gi".to_string()
+}
+
+fn default_packages_max_body_size() -> String {
+    "0".to_string()
+}
+
+fn default_packages_timeout() -> i64 {
+    600
+}
+
+fn default_storage_bucket() -> String {
+    "gitea".to_string()
+}
+
+fn default_storage_use_ssl() -> bool {
+    true
+}
+
+fn default_storage_targets() -> Vec<StorageTarget> {
+    vec![StorageTarget::Lfs, StorageTarget::Attachment]
+}
+
+/// CI server deployed alongside Gitea.
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct Ci {
+    #[serde(default)]
+    pub kind: CiKind,
+    /// Shared secret used by Woodpecker agents.
+    pub agent_secret: Option<String>,
+    /// Number of Woodpecker agents.
+    #[serde(default = "default_ci_agents")]
+    pub agents: i64,
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "::tf_bindgen::serde")]
+#[serde(rename_all = "lowercase")]
+pub enum CiKind {
+    #[default]
+    Jenkins,
+    Woodpecker,
+    None,
+}
+
+impl Default for Ci {
+    fn default() -> Self {
+        Self {
+            kind: CiKind::default(),
+            agent_secret: None,
+            agents: default_ci_agents(),
+        }
+    }
+}
+
+fn default_ci_agents() -> i64 {
+    1
+}
+
+/// Cache backend of Gitea.
+#[derive(Deserialize, Serialize, Default)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct Cache {
+    #[serde(default)]
+    pub kind: CacheKind,
+    /// Password of the Redis server. Generated if not set.
+    pub password: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "::tf_bindgen::serde")]
+#[serde(rename_all = "lowercase")]
+pub enum CacheKind {
+    #[default]
+    Memcached,
+    /// Also used to store sessions and queues.
+    Redis,
+    Memory,
+}
+
+/// Runs multiple Gitea replicas using a shared volume.
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct Ha {
+    #[serde(default = "default_ha_replicas")]
+    pub replicas: i64,
+    /// Storage class provisioning `ReadWriteMany` volumes (e.g. NFS or CephFS).
+    pub storage_class: String,
+    /// Size of the shared volume storing repositories.
+    #[serde(default = "default_ha_size")]
+    pub size: String,
+}
+
+fn default_ha_replicas() -> i64 {
+    2
+}
+
+fn default_ha_size() -> String {
+    "10Gi".to_string()
+}
+
+/// Replicated Postgres cluster storing the Gitea database. Uses a single instance if not set.
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct Database {
+    /// Number of Postgres pods including the primary.
+    #[serde(default = "default_database_replicas")]
+    pub replicas: i64,
+    /// Storage class provisioning the volume of each pod.
+    pub storage_class: String,
+    #[serde(default = "default_database_size")]
+    pub size: String,
+}
+
+fn default_database_replicas() -> i64 {
+    3
+}
+
+fn default_database_size() -> String {
+    "10Gi".to_string()
+}
+
+/// PgBouncer deployed in front of the Gitea database. Gitea connects using the pooler if set.
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct Pooler {
+    #[serde(default)]
+    pub mode: PoolMode,
+    /// Number of server connections.
+    #[serde(default = "default_pooler_pool_size")]
+    pub pool_size: i64,
+    #[serde(default = "default_pooler_max_client_conn")]
+    pub max_client_conn: i64,
+    /// Number of PgBouncer pods.
+    #[serde(default = "default_pooler_replicas")]
+    pub replicas: i64,
+}
+
+fn default_pooler_pool_size() -> i64 {
+    20
+}
+
+fn default_pooler_max_client_conn() -> i64 {
+    200
+}
+
+fn default_pooler_replicas() -> i64 {
+    2
+}
+
+/// Prometheus Operator scraping the metrics of all components using `PodMonitor` resources.
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct Monitoring {
+    /// Namespace running Prometheus.
+    #[serde(default = "default_monitoring_namespace")]
+    pub prometheus_namespace: String,
+    /// Labels added to the pod monitors (e.g. `release: kube-prometheus-stack`).
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    /// Deploys Prometheus, Alertmanager and Grafana into the namespace instead of relying on the
+    /// Prometheus Operator.
+    #[serde(default)]
+    pub bundled: bool,
+    /// Mail address receiving the alerts of the bundled Alertmanager. Requires the mail section.
+    pub alert_email: Option<String>,
+}
+
+fn default_monitoring_namespace() -> String {
+    "monitoring".to_string()
+}
+
+/// Fluent Bit collecting the logs of all containers in the namespace.
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct Logs {
+    #[serde(default)]
+    pub sink: LogSinkKind,
+    /// Directory of each node storing the logs of the `file` sink.
+    #[serde(default = "default_logs_path")]
+    pub path: String,
+    /// Size in MiB after which a file of the `file` sink is rotated.
+    #[serde(default = "default_logs_max_size")]
+    pub max_size: i64,
+    /// Days after which files of the `file` sink, which were not written, are deleted.
+    #[serde(default = "default_logs_retention_days")]
+    pub retention_days: i64,
+    /// URL of the Loki server (e.g. `http://loki.monitoring.svc:3100`).
+    pub url: Option<String>,
+    /// Host of the syslog server.
+    pub host: Option<String>,
+    #[serde(default = "default_syslog_port")]
+    pub port: i64,
+    #[serde(default)]
+    pub protocol: SyslogProtocol,
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "::tf_bindgen::serde")]
+#[serde(rename_all = "lowercase")]
+pub enum LogSinkKind {
+    #[default]
+    File,
+    Loki,
+    Syslog,
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "::tf_bindgen::serde")]
+#[serde(rename_all = "lowercase")]
+pub enum SyslogProtocol {
+    #[default]
+    Udp,
+    Tcp,
+}
+
+fn default_logs_path() -> String {
+    "/var/log/gitserver".to_string()
+}
+
+fn default_logs_max_size() -> i64 {
+    100
+}
+
+fn default_logs_retention_days() -> i64 {
+    7
+}
+
+fn default_syslog_port() -> i64 {
+    514
+}
+
+/// Network policies isolating the components. Components only accept connections from the
+/// ingress controller, Prometheus, the components using them and the sources in `allow`.
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct Network {
+    /// Namespace running the ingress controller.
+    #[serde(default = "default_ingress_namespace")]
+    pub ingress_namespace: String,
+    /// Labels of the ingress controller pods. All pods of `ingress_namespace` if empty.
+    #[serde(default)]
+    pub ingress_labels: HashMap<String, String>,
+    #[serde(default)]
+    pub allow: Vec<NetworkAllow>,
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        Self {
+            ingress_namespace: default_ingress_namespace(),
+            ingress_labels: HashMap::new(),
+            allow: Vec::new(),
+        }
+    }
+}
+
+/// Additional source allowed to connect to a component (e.g. a backup job reading `giteadb`).
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct NetworkAllow {
+    /// Name of the component (e.g. `gitea`, `giteadb`, `giteacache` or `jenkins`).
+    pub component: String,
+    /// Namespace of the source pods. Uses the namespace of the components if not set.
+    pub namespace: Option<String>,
+    /// Labels of the source pods. All pods of `namespace` if empty.
+    #[serde(default)]
+    pub pod_labels: HashMap<String, String>,
+    /// IP range in CIDR notation. Can not be combined with `namespace` and `pod_labels`.
+    pub cidr: Option<String>,
+}
+
+fn default_ingress_namespace() -> String {
+    "ingress-nginx".to_string()
+}
+
+/// Gitea Actions and the runners executing them.
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct Actions {
+    /// Number of runner pods.
+    #[serde(default = "default_runners")]
+    pub runners: i64,
+    #[serde(default)]
+    pub executor: RunnerExecutor,
+    /// Number of jobs executed concurrently by a single runner.
+    #[serde(default = "default_runner_capacity")]
+    pub capacity: i64,
+    /// Runner labels. Uses labels matching the executor if empty.
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// Storage class provisioning the volume storing the registration of each runner. Uses the
+    /// default storage class of the cluster if not set.
+    pub storage_class: Option<String>,
+    #[serde(default = "default_runner_size")]
+    pub size: String,
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy)]
+#[serde(crate = "::tf_bindgen::serde")]
+#[serde(rename_all = "lowercase")]
+pub enum RunnerExecutor {
+    #[default]
+    Dind,
+    #[serde(alias = "kubernetes")]
+    Host,
+}
+
+fn default_runners() -> i64 {
+    1
+}
+
+fn default_runner_capacity() -> i64 {
+    1
+}
+
+fn default_runner_size() -> String {
+    "1Gi".to_string()
+}
+
+/// Name of the Gitea authentication source registered for single sign-on.
+pub const SSO_SOURCE: &str = "sso";
+
+/// Single sign-on across Gitea and Jenkins using a bundled Dex OpenID Connect provider.
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct Sso {
+    pub gitea_client_secret: String,
+    /// Required if Jenkins is used as CI server.
+    pub jenkins_client_secret: Option<String>,
+    /// Members of this group are administrators of Gitea and Jenkins.
+    pub admin_group: Option<String>,
+    /// Members of these groups can read, build and cancel Jenkins jobs.
+    #[serde(default)]
+    pub developer_groups: Vec<String>,
+    /// Maps groups onto teams of Gitea organisations (group -> organisation -> teams).
+    #[serde(default)]
+    pub team_map: HashMap<String, HashMap<String, Vec<String>>>,
+    /// Upstream identity providers using Dex' connector format.
+    #[serde(default)]
+    pub connectors: Vec<json::Value>,
+    #[serde(default)]
+    pub users: Vec<SsoUser>,
+}
+
+/// Static user of the bundled identity provider.
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct SsoUser {
+    pub email: String,
+    pub username: String,
+    /// Bcrypt hash of the user's password.
+    pub hash: String,
+}
+
+impl Sso {
+    /// Returns the users in Dex' `staticPasswords` format.
+    pub fn static_passwords(&self) -> Vec<json::Value> {
+        self.users
+            .iter()
+            .map(|user| {
+                json::json!({
+                    "email": user.email,
+                    "username": user.username,
+                    "userID": user.username,
+                    "hash": user.hash
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the group team map in the format expected by Gitea.
+    pub fn group_team_map(&self) -> Option<String> {
+        group_team_map(&self.team_map)
+    }
+}
+
+/// SMTP server used by Gitea and Jenkins to send notifications.
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct Mail {
+    /// Required unless `catch_all` is set.
+    pub host: Option<String>,
+    #[serde(default = "default_mail_port")]
+    pub port: i64,
+    #[serde(default)]
+    pub tls: MailTls,
+    pub from: String,
+    pub user: Option<String>,
+    pub passwd: Option<String>,
+    /// Deploy an in-cluster catch-all SMTP server (Mailpit) and send all mails to it.
+    #[serde(default)]
+    pub catch_all: bool,
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy)]
+#[serde(crate = "::tf_bindgen::serde")]
+#[serde(rename_all = "lowercase")]
+pub enum MailTls {
+    None,
+    #[default]
+    StartTls,
+    Tls,
+}
+
+fn default_mail_port() -> i64 {
+    587
+}
+
+/// Terraform backend used to store the deployment state.
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum State {
+    /// Store the state in the local stack directory (or `path` if specified).
+    Local { path: Option<String> },
+    /// Store the state as Secret inside the Kubernetes cluster. Uses a Lease for locking.
+    Kubernetes {
+        secret_suffix: String,
+        #[serde(default = "default_state_namespace")]
+        namespace: String,
+    },
+    /// Store the state in a S3 compatible bucket (e.g. MinIO). Uses a lock file for locking.
+    S3 {
+        bucket: String,
+        #[serde(default = "default_state_key")]
+        key: String,
+        #[serde(default = "default_state_region")]
+        region: String,
+        endpoint: Option<String>,
+        access_key: Option<String>,
+        secret_key: Option<String>,
+    },
+    /// Store the state in a PostgreSQL database. Uses advisory locks for locking.
+    Pg {
+        conn_str: Option<String>,
+        schema_name: Option<String>,
+    },
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self::Local { path: None }
+    }
+}
+
+fn default_state_namespace() -> String {
+    "default".to_string()
+}
+
+fn default_state_key() -> String {
+    "gitserver/terraform.tfstate".to_string()
+}
+
+fn default_state_region() -> String {
+    "us-east-1".to_string()
+}
+
 impl Config {
     pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
         let content = std::fs::read_to_string(path).context("failed to read config file")?;
-        let config = toml::from_str(&content).context("failed to parse config file")?;
+        let mut config: Self = toml::from_str(&content).context("failed to parse config file")?;
+        config.validate()?;
+        config.mark_group_mapped_teams();
         Ok(config)
     }
+
+    /// Marks the teams assigned to groups by the group team maps of Dex or OpenID Connect sources.
+    fn mark_group_mapped_teams(&mut self) {
+        let maps = self
+            .sso
+            .iter()
+            .map(|sso| &sso.team_map)
+            .chain(self.gitea.auth.oidc.iter().map(|oidc| &oidc.group_team_map));
+        let mapped: Vec<(&String, &String)> = maps
+            .flat_map(|map| map.values())
+            .flat_map(|orgs| orgs.iter())
+            .flat_map(|(org, teams)| teams.iter().map(move |team| (org, team)))
+            .collect();
+        for team in &mut self.gitea.teams {
+            team.group_mapped = mapped.contains(&(&team.org, &team.name));
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if let Some(Mail {
+            host: None,
+            catch_all: false,
+            ..
+        }) = &self.mail
+        {
+            bail!("mail.host: required unless mail.catch_all is set")
+        }
+        if let Some(actions) = &self.actions {
+            if actions.runners < 0 || actions.capacity < 1 {
+                bail!("actions: expected a non-negative number of runners and a positive capacity")
+            }
+        }
+        if let Some(storage) = &self.storage {
+            let external = [&storage.endpoint, &storage.access_key, &storage.secret_key];
+            if !storage.minio && external.iter().any(|value| value.is_none()) {
+                bail!("storage: endpoint, access_key and secret_key are required without minio")
+            }
+        }
+        if let Some(Packages {
+            bucket: Some(_), ..
+        }) = &self.packages
+        {
+            if self.storage.is_none() {
+                bail!("packages.bucket: requires the storage section")
+            }
+        }
+        if let Some(Monitoring {
+            alert_email: Some(_),
+            bundled,
+            ..
+        }) = &self.monitoring
+        {
+            if !bundled {
+                bail!("monitoring.alert_email: requires monitoring.bundled")
+            }
+            if self.mail.is_none() {
+                bail!("monitoring.alert_email: requires the mail section")
+            }
+        }
+        if let Some(ha) = &self.ha {
+            if ha.replicas < 1 {
+                bail!("ha.replicas: expected a positive number of replicas")
+            }
+            if self.cache.kind != CacheKind::Redis {
+                bail!("ha: requires redis as cache to share sessions and queues")
+            }
+            if let Some(Indexer {
+                kind: IndexerKind::Bleve,
+                ..
+            }) = &self.indexer
+            {
+                bail!("ha: the bleve index can not be shared, use elasticsearch instead")
+            }
+        }
+        if let Some(database) = &self.database {
+            if database.replicas < 2 {
+                bail!("database.replicas: expected at least two replicas")
+            }
+        }
+        if let Some(pooler) = &self.pooler {
+            if pooler.pool_size < 1 || pooler.max_client_conn < 1 {
+                bail!("pooler: expected a positive pool size and number of client connections")
+            }
+            if pooler.replicas < 1 {
+                bail!("pooler.replicas: expected a positive number of replicas")
+            }
+        }
+        if self.cache.kind != CacheKind::Redis && self.cache.password.is_some() {
+            bail!("cache.password: only supported by redis")
+        }
+        if let Some(Indexer {
+            kind: IndexerKind::Bleve,
+            url: Some(_),
+            ..
+        }) = &self.indexer
+        {
+            bail!("indexer.url: only supported by elasticsearch")
+        }
+        if let Some(logs) = &self.logs {
+            match logs.sink {
+                LogSinkKind::File if logs.max_size < 1 || logs.retention_days < 1 => {
+                    bail!("logs: expected a positive max_size and retention_days")
+                }
+                LogSinkKind::Loki => {
+                    let url = logs.url.as_deref().context("logs.url: required by loki")?;
+                    let url = Url::parse(url).context("logs.url: invalid URL")?;
+                    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+                        bail!("logs.url: expected a http or https URL")
+                    }
+                    if !url.username().is_empty() || url.password().is_some() {
+                        bail!("logs.url: credentials are not supported")
+                    }
+                }
+                LogSinkKind::Syslog if logs.host.is_none() => {
+                    bail!("logs.host: required by syslog")
+                }
+                _ => {}
+            }
+        }
+        for allow in &self.network.allow {
+            let component = &allow.component;
+            let pods = allow.namespace.is_some() || !allow.pod_labels.is_empty();
+            match (&allow.cidr, pods) {
+                (Some(_), true) => bail!(
+                    "network.allow.{component}: cidr can not be combined with namespace or pod_labels"
+                ),
+                (None, false) => {
+                    bail!("network.allow.{component}: requires namespace, pod_labels or cidr")
+                }
+                _ => {}
+            }
+        }
+        if self.ci.kind == CiKind::Woodpecker && self.ci.agent_secret.is_none() {
+            bail!("ci.agent_secret: required by woodpecker")
+        }
+        if let Some(sso) = &self.sso {
+            if self.ci.kind == CiKind::Jenkins && sso.jenkins_client_secret.is_none() {
+                bail!("sso.jenkins_client_secret: required by jenkins")
+            }
+        }
+        let sources = self.gitea.auth.sources();
+        if self.sso.is_some() && sources.iter().any(|source| source.name() == SSO_SOURCE) {
+            bail!("gitea.auth: authentication source '{SSO_SOURCE}' is reserved for sso")
+        }
+        for (i, source) in sources.iter().enumerate() {
+            if sources[..i]
+                .iter()
+                .any(|other| other.name() == source.name())
+            {
+                bail!(
+                    "gitea.auth: duplicate authentication source '{}'",
+                    source.name()
+                )
+            }
+        }
+        for (section, values) in &self.gitea.app_ini {
+            for key in values.keys() {
+                if crate::construct::gitea::is_managed_setting(section, key) {
+                    bail!("gitea.app_ini.{section}.{key}: setting is managed by gitserver")
+                }
+            }
+        }
+        Ok(())
+    }
 }
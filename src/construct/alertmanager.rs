@@ -0,0 +1,212 @@
+use std::rc::Rc;
+
+use tf_bindgen::codegen::{resource, Construct};
+use tf_bindgen::json;
+use tf_bindgen::{Scope, Value};
+use tf_kubernetes::kubernetes::resource::{
+    kubernetes_deployment, kubernetes_secret, kubernetes_service,
+};
+
+use super::mail::{MailConfig, MailTls};
+use super::network_policy::{NetworkEndpoint, NetworkPeer};
+use super::security;
+
+const HTTP_PORT: i64 = 9093;
+
+/// [Alertmanager](https://prometheus.io/docs/alerting/latest/alertmanager/) sending the alerts of
+/// Prometheus to `receiver` by mail. Alerts are only shown in Grafana if no receiver is set.
+/// Silences are lost on restart.
+#[derive(Construct)]
+#[construct(builder)]
+#[allow(dead_code)]
+pub struct Alertmanager {
+    #[construct(id)]
+    name: String,
+    #[construct(scope)]
+    scope: Rc<dyn Scope>,
+    #[construct(setter(into_value))]
+    namespace: Value<String>,
+    #[construct(setter(into))]
+    mail: Option<MailConfig>,
+    /// Mail address receiving all alerts. Requires `mail`.
+    #[construct(setter(into))]
+    receiver: Option<String>,
+}
+
+impl Alertmanager {
+    /// Returns the in-cluster address used by Prometheus to send alerts.
+    pub fn address(&self) -> String {
+        format!("{}.{}.svc:{HTTP_PORT}", self.name, self.namespace.get())
+    }
+
+    /// Returns the in-cluster URL of the HTTP API.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.address())
+    }
+
+    /// Returns the HTTP API as endpoint of network policies.
+    pub fn endpoint(&self) -> NetworkEndpoint {
+        NetworkEndpoint::app(format!("alertmanager-{}", self.name), [HTTP_PORT])
+    }
+
+    /// Returns the Alertmanager pods as source of network policies.
+    pub fn peer(&self) -> NetworkPeer {
+        NetworkPeer::app(format!("alertmanager-{}", self.name))
+    }
+}
+
+impl AlertmanagerBuilder {
+    pub fn build(&mut self) -> Rc<Alertmanager> {
+        let this = Rc::new(Alertmanager {
+            name: self.name.clone(),
+            scope: self.scope.clone(),
+            namespace: self.namespace.clone().expect("missing field 'namespace'"),
+            mail: self.mail.clone().flatten(),
+            receiver: self.receiver.clone().flatten(),
+        });
+        assert!(
+            this.receiver.is_none() || this.mail.is_some(),
+            "alert receiver requires a mail server"
+        );
+
+        let name = &this.name;
+        let labels = crate::map! {
+            "app" = format!("alertmanager-{name}")
+        };
+
+        let mut receiver = json::json!({ "name": "default" });
+        if let (Some(mail), Some(to)) = (&this.mail, &this.receiver) {
+            receiver["email_configs"] = json::json!([email_config(mail, to)]);
+        }
+        let config = json::json!({
+            "route": {
+                "receiver": "default",
+                "group_by": ["alertname", "pod"],
+                "repeat_interval": "12h"
+            },
+            "receivers": [receiver]
+        });
+        let config = crate::helper::yaml(&config);
+        let checksum = crate::helper::checksum(&config);
+        // Stored as secret, since it may contain the SMTP password.
+        let config = resource! {
+            &this, resource "kubernetes_secret" "alertmanager-config" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-config")
+                }
+                data = crate::map! {
+                    "alertmanager.yml" = config
+                }
+            }
+        };
+
+        resource! {
+            &this, resource "kubernetes_service" "alertmanager" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+                spec {
+                    selector = &labels
+                    port {
+                        name = "http"
+                        port = HTTP_PORT
+                    }
+                }
+            }
+        };
+
+        resource! {
+            &this, resource "kubernetes_deployment" "alertmanager" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+                spec {
+                    replicas = "1"
+                    selector {
+                        match_labels = &labels
+                    }
+                    template {
+                        metadata {
+                            labels = &labels
+                            annotations = crate::map! {
+                                "gitserver/checksum" = checksum
+                            }
+                        }
+                        spec {
+                            security_context = security::deployment::pod(65534, 65534)
+                            container {
+                                name = "alertmanager"
+                                image = "prom/alertmanager:v0.26.0"
+                                args = [
+                                    "--config.file=/etc/alertmanager/alertmanager.yml",
+                                    "--storage.path=/alertmanager"
+                                ]
+                                port {
+                                    name = "http"
+                                    container_port = HTTP_PORT
+                                }
+                                volume_mount {
+                                    name = "config"
+                                    mount_path = "/etc/alertmanager"
+                                }
+                                volume_mount {
+                                    name = "data"
+                                    mount_path = "/alertmanager"
+                                }
+                                readiness_probe {
+                                    http_get {
+                                        path = "/-/ready"
+                                        port = "http"
+                                    }
+                                }
+                                liveness_probe {
+                                    http_get {
+                                        path = "/-/healthy"
+                                        port = "http"
+                                    }
+                                    success_threshold = 1
+                                    failure_threshold = 10
+                                    period_seconds = 12
+                                    timeout_seconds = 5
+                                }
+                                security_context = security::deployment::container()
+                            }
+                            volume {
+                                name = "config"
+                                secret {
+                                    secret_name = &config.metadata[0].name
+                                }
+                            }
+                            volume {
+                                name = "data"
+                                empty_dir {}
+                            }
+                        }
+                    }
+                }
+            }
+        };
+
+        this
+    }
+}
+
+/// Returns the Alertmanager configuration sending alerts to `to` using `mail`. Implicit TLS is
+/// used by Alertmanager if the server listens on port 465.
+fn email_config(mail: &MailConfig, to: &str) -> json::Value {
+    let mut config = json::json!({
+        "to": to,
+        "from": &mail.from,
+        "smarthost": format!("{}:{}", mail.host, mail.port),
+        "require_tls": mail.tls == MailTls::StartTls,
+        "send_resolved": true
+    });
+    if let Some(user) = &mail.user {
+        config["auth_username"] = json::json!(user);
+        config["auth_password"] = json::json!(mail.password.clone().unwrap_or_default());
+    }
+    config
+}
@@ -0,0 +1,35 @@
+/// Backend used by Gitea to cache data. Redis additionally stores sessions and queues, which
+/// allows running multiple Gitea replicas.
+#[derive(Clone, Default)]
+pub enum Cache {
+    /// In-memory cache of each Gitea instance. Sessions and queues are stored on the volume.
+    #[default]
+    Memory,
+    /// Memcached server reachable using the given host and port.
+    Memcached(String),
+    /// Redis server reachable using the given URL (including password), e.g.
+    /// `redis://:password@redis:6379`.
+    Redis(String),
+}
+
+impl Cache {
+    /// Returns the `app.ini` settings (section, key and value) used to configure this backend.
+    pub(crate) fn settings(&self) -> Vec<(&'static str, &'static str, String)> {
+        match self {
+            Cache::Memory => vec![("cache", "ADAPTER", "memory".to_string())],
+            Cache::Memcached(host) => vec![
+                ("cache", "ADAPTER", "memcache".to_string()),
+                ("cache", "HOST", host.clone()),
+            ],
+            // Separate databases are used to avoid key collisions.
+            Cache::Redis(url) => vec![
+                ("cache", "ADAPTER", "redis".to_string()),
+                ("cache", "HOST", format!("{url}/0")),
+                ("session", "PROVIDER", "redis".to_string()),
+                ("session", "PROVIDER_CONFIG", format!("{url}/1")),
+                ("queue", "TYPE", "redis".to_string()),
+                ("queue", "CONN_STR", format!("{url}/2")),
+            ],
+        }
+    }
+}
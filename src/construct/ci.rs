@@ -0,0 +1,25 @@
+use super::ingress::IngressServiceConfig;
+use super::network_policy::{NetworkEndpoint, NetworkPeer};
+
+/// CI server deployed alongside Gitea.
+pub trait CiServer {
+    /// Returns a human readable name of the CI server.
+    fn kind(&self) -> &'static str;
+
+    /// Returns the external URL of the web interface.
+    fn url(&self) -> String;
+
+    fn ingress(&self) -> IngressServiceConfig;
+
+    /// Returns the server as endpoint of network policies.
+    fn endpoint(&self) -> NetworkEndpoint;
+
+    /// Returns the server pods as source of network policies.
+    fn peer(&self) -> NetworkPeer;
+
+    /// Returns the redirect URI of the OAuth2 application used to sign in with Gitea. `None` if
+    /// the CI server does not use Gitea as identity provider.
+    fn gitea_redirect_uri(&self) -> Option<String> {
+        None
+    }
+}
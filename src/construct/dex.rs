@@ -0,0 +1,272 @@
+use std::rc::Rc;
+
+use tf_bindgen::codegen::{resource, Construct};
+use tf_bindgen::json;
+use tf_bindgen::{Scope, Value};
+use tf_kubernetes::kubernetes::resource::{
+    kubernetes_deployment, kubernetes_secret, kubernetes_service,
+};
+
+use super::ingress::IngressServiceConfig;
+use super::monitoring::{Monitoring, PodMonitor};
+use super::network_policy::{NetworkEndpoint, NetworkPeer};
+use super::security;
+
+const HTTP_PORT: i64 = 5556;
+const TELEMETRY_PORT: i64 = 5558;
+
+/// OAuth2 client registered in Dex.
+#[derive(Clone)]
+pub struct DexClient {
+    pub id: String,
+    pub name: String,
+    pub secret: String,
+    pub redirect_uris: Vec<String>,
+}
+
+/// [Dex](https://dexidp.io/) OpenID Connect provider used as single sign-on for all components.
+/// Uses Postgres as storage.
+#[derive(Construct)]
+#[construct(builder)]
+#[allow(dead_code)]
+pub struct Dex {
+    #[construct(id)]
+    name: String,
+    #[construct(scope)]
+    scope: Rc<dyn Scope>,
+    #[construct(setter(into_value))]
+    namespace: Value<String>,
+    #[construct(setter(into))]
+    domain: String,
+    #[construct(setter(into))]
+    path: String,
+    #[construct(setter(into_value))]
+    db_host: Value<String>,
+    #[construct(setter(into_value))]
+    db_name: Value<String>,
+    #[construct(setter(into_value))]
+    db_user: Value<String>,
+    #[construct(setter(into_value))]
+    db_password: Value<String>,
+    #[construct(setter(into))]
+    clients: Vec<DexClient>,
+    /// Upstream identity providers using Dex' connector configuration format.
+    #[construct(setter(into))]
+    connectors: Vec<json::Value>,
+    /// Static users using Dex' `staticPasswords` format. Enables the password database if not
+    /// empty.
+    #[construct(setter(into))]
+    static_passwords: Vec<json::Value>,
+    /// Scrapes the telemetry endpoint using Prometheus.
+    #[construct(setter(into))]
+    monitoring: Option<Monitoring>,
+}
+
+impl Dex {
+    /// Returns the issuer URL of this provider.
+    pub fn issuer(&self) -> String {
+        format!("https://{}{}", self.domain, self.path)
+    }
+
+    /// Returns the URL of the OpenID Connect discovery document.
+    pub fn discovery_url(&self) -> String {
+        format!("{}/.well-known/openid-configuration", self.issuer())
+    }
+
+    /// Returns the HTTP server as endpoint of network policies.
+    pub fn endpoint(&self) -> NetworkEndpoint {
+        NetworkEndpoint::app(format!("dex-{}", self.name), [HTTP_PORT])
+    }
+
+    /// Returns the Dex pods as source of network policies.
+    pub fn peer(&self) -> NetworkPeer {
+        NetworkPeer::app(format!("dex-{}", self.name))
+    }
+
+    pub fn ingress(&self) -> IngressServiceConfig {
+        IngressServiceConfig {
+            rewrite: false,
+            path: self.path.clone(),
+            service_name: self.name.clone(),
+            service_port: HTTP_PORT,
+            pod_labels: self.endpoint().pod_labels,
+            routed_paths: Vec::new(),
+            blocked_paths: Vec::new(),
+        }
+    }
+}
+
+impl DexBuilder {
+    pub fn build(&mut self) -> Rc<Dex> {
+        let this = Rc::new(Dex {
+            name: self.name.clone(),
+            scope: self.scope.clone(),
+            namespace: self.namespace.clone().expect("missing field 'namespace'"),
+            domain: self.domain.clone().expect("missing field 'domain'"),
+            path: self.path.clone().unwrap_or("/dex".into()),
+            db_host: self.db_host.clone().expect("missing field 'db_host'"),
+            db_name: self.db_name.clone().expect("missing field 'db_name'"),
+            db_user: self.db_user.clone().expect("missing field 'db_user'"),
+            db_password: self
+                .db_password
+                .clone()
+                .expect("missing field 'db_password'"),
+            clients: self.clients.clone().unwrap_or_default(),
+            connectors: self.connectors.clone().unwrap_or_default(),
+            static_passwords: self.static_passwords.clone().unwrap_or_default(),
+            monitoring: self.monitoring.clone().flatten(),
+        });
+
+        let name = &this.name;
+        let labels = crate::map! {
+            "app" = format!("dex-{name}")
+        };
+
+        let clients: Vec<_> = this
+            .clients
+            .iter()
+            .map(|client| {
+                json::json!({
+                    "id": client.id,
+                    "name": client.name,
+                    "secret": client.secret,
+                    "redirectURIs": client.redirect_uris
+                })
+            })
+            .collect();
+        let dex_config = json::json!({
+            "issuer": this.issuer(),
+            "storage": {
+                "type": "postgres",
+                "config": {
+                    "host": this.db_host.get().as_str(),
+                    "port": 5432,
+                    "database": this.db_name.get().as_str(),
+                    "user": this.db_user.get().as_str(),
+                    "password": this.db_password.get().as_str(),
+                    "ssl": { "mode": "disable" }
+                }
+            },
+            "web": { "http": format!("0.0.0.0:{HTTP_PORT}") },
+            "telemetry": { "http": format!("0.0.0.0:{TELEMETRY_PORT}") },
+            "oauth2": { "skipApprovalScreen": true },
+            "staticClients": clients,
+            "connectors": this.connectors,
+            "enablePasswordDB": !this.static_passwords.is_empty(),
+            "staticPasswords": this.static_passwords
+        });
+        let dex_config = crate::helper::yaml(&dex_config);
+        let checksum = crate::helper::checksum(&dex_config);
+
+        let config = resource! {
+            &this, resource "kubernetes_secret" "dex-config" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-config")
+                }
+                data = crate::map! {
+                    "config.yaml" = dex_config
+                }
+            }
+        };
+
+        resource! {
+            &this, resource "kubernetes_service" "dex" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+                spec {
+                    selector = &labels
+                    port {
+                        name = "http"
+                        port = HTTP_PORT
+                    }
+                }
+            }
+        };
+
+        resource! {
+            &this, resource "kubernetes_deployment" "dex" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+                spec {
+                    replicas = "1"
+                    selector {
+                        match_labels = &labels
+                    }
+                    template {
+                        metadata {
+                            labels = &labels
+                            annotations = crate::map! {
+                                "gitserver/checksum" = checksum
+                            }
+                        }
+                        spec {
+                            security_context = security::deployment::pod(1001, 1001)
+                            container {
+                                name = "dex"
+                                image = "ghcr.io/dexidp/dex:v2.37.0"
+                                command = ["dex", "serve", "/etc/dex/config.yaml"]
+                                port {
+                                    name = "http"
+                                    container_port = HTTP_PORT
+                                }
+                                port {
+                                    name = "telemetry"
+                                    container_port = TELEMETRY_PORT
+                                }
+                                env {
+                                    // Password hashes contain `$` and must not be expanded.
+                                    name = "DEX_EXPAND_ENV"
+                                    value = "false"
+                                }
+                                volume_mount {
+                                    name = "config"
+                                    mount_path = "/etc/dex"
+                                }
+                                readiness_probe {
+                                    http_get {
+                                        path = format!("{}/healthz", this.path)
+                                        port = "http"
+                                    }
+                                }
+                                liveness_probe {
+                                    http_get {
+                                        path = format!("{}/healthz", this.path)
+                                        port = "http"
+                                    }
+                                    success_threshold = 1
+                                    failure_threshold = 10
+                                    period_seconds = 12
+                                    timeout_seconds = 5
+                                }
+                                security_context = security::deployment::container()
+                            }
+                            volume {
+                                name = "config"
+                                secret {
+                                    secret_name = &config.metadata[0].name
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        };
+
+        if let Some(monitoring) = &this.monitoring {
+            PodMonitor::create(&this, format!("{name}-metrics"))
+                .namespace(&this.namespace)
+                .monitoring(monitoring.clone())
+                .pod_labels([("app".to_string(), format!("dex-{name}"))])
+                .port("telemetry")
+                .metrics_port(TELEMETRY_PORT)
+                .build();
+        }
+
+        this
+    }
+}
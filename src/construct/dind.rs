@@ -0,0 +1,59 @@
+//! Docker-in-Docker sidecar used to run CI jobs in containers. Every workload kind uses distinct
+//! generated types, so the functions are generated for each of them (e.g.
+//! [`deployment::sidecar`]).
+
+const DOCKER_PORT: i64 = 2375;
+
+macro_rules! dind {
+    (
+        $module:ident, $resource:ident,
+        container: $container:ident, $env:ident, $security_context:ident $(,)?
+    ) => {
+        #[allow(dead_code)]
+        pub mod $module {
+            use tf_kubernetes::kubernetes::resource::$resource::{
+                $container, $env, $security_context,
+            };
+
+            /// Returns the environment variables used to connect to the daemon started by
+            /// [`sidecar`].
+            pub fn docker_env() -> Vec<$env> {
+                vec![$env::builder()
+                    .name("DOCKER_HOST")
+                    .value(format!("tcp://localhost:{}", super::DOCKER_PORT))
+                    .build()]
+            }
+
+            /// Returns a privileged Docker-in-Docker container running as root. The daemon is only
+            /// reachable from inside the pod, so TLS is disabled.
+            pub fn sidecar() -> $container {
+                let tls_dir = $env::builder().name("DOCKER_TLS_CERTDIR").value("").build();
+                let security_context = $security_context::builder()
+                    .privileged(true)
+                    .run_as_user("0")
+                    .run_as_non_root(false)
+                    .build();
+                $container::builder()
+                    .name("docker")
+                    .image("docker:24.0.6-dind")
+                    .env(vec![tls_dir])
+                    .security_context(security_context)
+                    .build()
+            }
+        }
+    };
+}
+
+dind!(
+    deployment, kubernetes_deployment,
+    container: KubernetesDeploymentSpecTemplateSpecContainer,
+        KubernetesDeploymentSpecTemplateSpecContainerEnv,
+        KubernetesDeploymentSpecTemplateSpecContainerSecurityContext,
+);
+
+dind!(
+    stateful_set, kubernetes_stateful_set,
+    container: KubernetesStatefulSetSpecTemplateSpecContainer,
+        KubernetesStatefulSetSpecTemplateSpecContainerEnv,
+        KubernetesStatefulSetSpecTemplateSpecContainerSecurityContext,
+);
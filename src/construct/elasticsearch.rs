@@ -0,0 +1,194 @@
+use std::rc::Rc;
+
+use tf_bindgen::codegen::{resource, Construct};
+use tf_bindgen::{Scope, Value};
+use tf_kubernetes::kubernetes::resource::kubernetes_service;
+use tf_kubernetes::kubernetes::resource::kubernetes_stateful_set::{
+    self, KubernetesStatefulSetSpecTemplateSpecContainer,
+    KubernetesStatefulSetSpecTemplateSpecContainerPort,
+};
+
+use super::monitoring::{Monitoring, PodMonitor};
+use super::network_policy::NetworkEndpoint;
+use super::security;
+
+const HTTP_PORT: i64 = 9200;
+const METRICS_PORT: i64 = 9114;
+
+/// Single node [Elasticsearch](https://www.elastic.co/elasticsearch) cluster used by Gitea to
+/// index code and issues. Security is disabled, so the cluster must only be reachable from inside
+/// the namespace.
+#[derive(Construct)]
+#[construct(builder)]
+#[allow(dead_code)]
+pub struct Elasticsearch {
+    #[construct(id)]
+    name: String,
+    #[construct(scope)]
+    scope: Rc<dyn Scope>,
+    #[construct(setter(into_value))]
+    namespace: Value<String>,
+    #[construct(setter(into_value))]
+    volume_claim: Value<String>,
+    /// Heap size of the JVM (e.g. `512m`).
+    #[construct(setter(into))]
+    heap_size: String,
+    /// Adds an `elasticsearch_exporter` sidecar scraped by Prometheus.
+    #[construct(setter(into))]
+    monitoring: Option<Monitoring>,
+}
+
+impl Elasticsearch {
+    /// Returns the URL used to connect to this cluster.
+    pub fn url(&self) -> String {
+        format!(
+            "http://{}.{}.svc:{HTTP_PORT}",
+            self.name,
+            self.namespace.get()
+        )
+    }
+
+    /// Returns the HTTP API as endpoint of network policies.
+    pub fn endpoint(&self) -> NetworkEndpoint {
+        NetworkEndpoint::app(format!("elasticsearch-{}", self.name), [HTTP_PORT])
+    }
+}
+
+impl ElasticsearchBuilder {
+    pub fn build(&mut self) -> Rc<Elasticsearch> {
+        let this = Rc::new(Elasticsearch {
+            name: self.name.clone(),
+            scope: self.scope.clone(),
+            namespace: self.namespace.clone().expect("missing field 'namespace'"),
+            volume_claim: self
+                .volume_claim
+                .clone()
+                .expect("missing field 'volume_claim'"),
+            heap_size: self.heap_size.clone().unwrap_or("512m".into()),
+            monitoring: self.monitoring.clone().flatten(),
+        });
+
+        let name = &this.name;
+        let labels = crate::map! {
+            "app" = format!("elasticsearch-{name}")
+        };
+
+        let mut sidecars = Vec::new();
+        if let Some(monitoring) = &this.monitoring {
+            let port = KubernetesStatefulSetSpecTemplateSpecContainerPort::builder()
+                .name("metrics")
+                .container_port(METRICS_PORT)
+                .build();
+            sidecars.push(
+                KubernetesStatefulSetSpecTemplateSpecContainer::builder()
+                    .name("exporter")
+                    .image("quay.io/prometheuscommunity/elasticsearch-exporter:v1.6.0")
+                    .args(vec![format!("--es.uri=http://localhost:{HTTP_PORT}")])
+                    .port(vec![port])
+                    .security_context(security::stateful_set::container())
+                    .build(),
+            );
+            PodMonitor::create(&this, format!("{name}-metrics"))
+                .namespace(&this.namespace)
+                .monitoring(monitoring.clone())
+                .pod_labels([("app".to_string(), format!("elasticsearch-{name}"))])
+                .metrics_port(METRICS_PORT)
+                .build();
+        }
+
+        let service = resource! {
+            &this, resource "kubernetes_service" "elasticsearch" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+                spec {
+                    selector = &labels
+                    port {
+                        name = "http"
+                        port = HTTP_PORT
+                    }
+                }
+            }
+        };
+
+        resource! {
+            &this, resource "kubernetes_stateful_set" "elasticsearch" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+                spec {
+                    replicas = "1"
+                    service_name = &service.metadata[0].name
+                    selector {
+                        match_labels = &labels
+                    }
+                    template {
+                        metadata {
+                            labels = &labels
+                        }
+                        spec {
+                            security_context = security::stateful_set::pod(1000, 1000)
+                            container {
+                                name = "elasticsearch"
+                                image = "elasticsearch:7.17.15"
+                                port {
+                                    name = "http"
+                                    container_port = HTTP_PORT
+                                }
+                                env {
+                                    name = "discovery.type"
+                                    value = "single-node"
+                                }
+                                env {
+                                    name = "xpack.security.enabled"
+                                    value = "false"
+                                }
+                                // Avoids raising `vm.max_map_count` on the node.
+                                env {
+                                    name = "node.store.allow_mmap"
+                                    value = "false"
+                                }
+                                env {
+                                    name = "ES_JAVA_OPTS"
+                                    value = format!("-Xms{0} -Xmx{0}", this.heap_size)
+                                }
+                                volume_mount {
+                                    name = "data"
+                                    mount_path = "/usr/share/elasticsearch/data"
+                                }
+                                readiness_probe {
+                                    http_get {
+                                        path = "/_cluster/health?local=true"
+                                        port = "http"
+                                    }
+                                }
+                                liveness_probe {
+                                    tcp_socket {
+                                        port = "http"
+                                    }
+                                    success_threshold = 1
+                                    failure_threshold = 10
+                                    period_seconds = 12
+                                    timeout_seconds = 5
+                                }
+                                // Elasticsearch creates its keystore inside the config directory.
+                                security_context = security::stateful_set::writable_container()
+                            }
+                            container = sidecars
+                            volume {
+                                name = "data"
+                                persistent_volume_claim {
+                                    claim_name = &this.volume_claim
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        };
+
+        this
+    }
+}
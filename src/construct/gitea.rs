@@ -1,13 +1,33 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
 
 use tf_bindgen::codegen::{resource, Construct};
 use tf_bindgen::value::IntoValue;
 use tf_bindgen::{Scope, Value};
+use tf_kubernetes::kubernetes::resource::kubernetes_stateful_set::{
+    KubernetesStatefulSetSpecTemplateSpecContainerVolumeMount,
+    KubernetesStatefulSetSpecTemplateSpecVolume,
+    KubernetesStatefulSetSpecTemplateSpecVolumePersistentVolumeClaim,
+};
 use tf_kubernetes::kubernetes::resource::{
-    kubernetes_config_map, kubernetes_secret, kubernetes_service, kubernetes_stateful_set,
+    kubernetes_config_map, kubernetes_pod_disruption_budget_v1, kubernetes_secret,
+    kubernetes_service, kubernetes_stateful_set,
 };
 
+use super::cache::Cache;
+use super::gitea_auth::{auth_script, AuthSource};
+use super::gitea_bootstrap::{GiteaBootstrap, GiteaBootstrapBuilder};
+use super::gitea_runner::{GiteaRunner, GiteaRunnerBuilder};
+use super::grafana::{Grafana, GrafanaBuilder};
+use super::indexer::Indexer;
 use super::ingress::IngressServiceConfig;
+use super::mail::{MailConfig, MailTls};
+use super::monitoring::{MetricsToken, Monitoring, PodMonitor};
+use super::network_policy::{NetworkEndpoint, NetworkPeer};
+use super::object_storage::ObjectStorage;
+use super::security;
+use super::woodpecker::{Woodpecker, WoodpeckerBuilder};
 
 const INIT_SCRIPT: &str =
     include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/script/gitea/init.sh"));
@@ -15,6 +35,139 @@ const MIGRATION_SCRIPT: &str = include_str!(concat!(
     env!("CARGO_MANIFEST_DIR"),
     "/script/gitea/migrate.sh"
 ));
+const AUTH_SCRIPT: &str =
+    include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/script/gitea/auth.sh"));
+
+/// Gitea image. Upgrading runs the database migrations on start (see "Upgrading" in the README).
+const GITEA_IMAGE: &str = "gitea/gitea:1.21.11-rootless";
+const SSH_PORT: i64 = 2222;
+const INDEXER_PATH: &str = "/gitea/indexers";
+/// Pod specific copy of `app.ini`. The shared `app.ini` stored on the volume is copied here on
+/// start, so settings of a single replica can be changed.
+const POD_APP_INI: &str = "/etc/gitea/app.ini";
+
+/// `app.ini` settings (section and key) managed by this construct. These must not be overwritten
+/// using [`GiteaBuilder::app_ini`].
+pub const MANAGED_SETTINGS: &[(&str, &str)] = &[
+    ("database", "*"),
+    ("server", "ROOT_URL"),
+    ("cache", "ADAPTER"),
+    ("cache", "HOST"),
+    ("session", "PROVIDER"),
+    ("session", "PROVIDER_CONFIG"),
+    ("queue", "TYPE"),
+    ("queue", "CONN_STR"),
+    ("mailer", "*"),
+    ("security", "INSTALL_LOCK"),
+    ("security", "SECRET_KEY"),
+    ("security", "INTERNAL_TOKEN"),
+    ("actions", "ENABLED"),
+    ("storage", "*"),
+    ("lfs", "STORAGE_TYPE"),
+    ("lfs", "MINIO_*"),
+    ("attachment", "STORAGE_TYPE"),
+    ("attachment", "MINIO_*"),
+    ("packages", "ENABLED"),
+    ("packages", "STORAGE_TYPE"),
+    ("packages", "MINIO_*"),
+    ("indexer", "REPO_INDEXER_ENABLED"),
+    ("indexer", "REPO_INDEXER_TYPE"),
+    ("indexer", "REPO_INDEXER_PATH"),
+    ("indexer", "REPO_INDEXER_CONN_STR"),
+    ("indexer", "ISSUE_INDEXER_TYPE"),
+    ("indexer", "ISSUE_INDEXER_PATH"),
+    ("indexer", "ISSUE_INDEXER_CONN_STR"),
+    ("metrics", "ENABLED"),
+    ("metrics", "TOKEN"),
+];
+
+/// `app.ini` sections able to store their data using [`GiteaBuilder::object_storage`].
+pub const OBJECT_STORAGE_SECTIONS: &[&str] = &["storage", "lfs", "attachment", "packages"];
+
+/// Returns `true` if the setting `key` of `section` is managed by this construct.
+pub fn is_managed_setting(section: &str, key: &str) -> bool {
+    MANAGED_SETTINGS
+        .iter()
+        .any(|(managed_section, managed_key)| {
+            let key_matches = match managed_key.strip_suffix('*') {
+                Some(prefix) => key.to_uppercase().starts_with(prefix),
+                None => managed_key.eq_ignore_ascii_case(key),
+            };
+            managed_section.eq_ignore_ascii_case(section) && key_matches
+        })
+}
+
+/// Returns `true` if the setting `key` contains sensitive information and must be stored inside a
+/// secret.
+fn is_secret_setting(key: &str) -> bool {
+    let key = key.to_uppercase();
+    ["PASSWD", "PASSWORD", "SECRET", "TOKEN", "CONN_STR"]
+        .iter()
+        .any(|pattern| key.contains(pattern))
+}
+
+/// Returns the `[mailer]` settings used to send mails using `mail`.
+fn mailer_settings(mail: &MailConfig) -> Vec<(&'static str, String)> {
+    let protocol = match mail.tls {
+        MailTls::None => "smtp",
+        MailTls::StartTls => "smtp+starttls",
+        MailTls::Tls => "smtps",
+    };
+    let mut settings = vec![
+        ("ENABLED", "true".to_string()),
+        ("PROTOCOL", protocol.to_string()),
+        ("SMTP_ADDR", mail.host.clone()),
+        ("SMTP_PORT", mail.port.to_string()),
+        ("FROM", mail.from.clone()),
+    ];
+    if let Some(user) = &mail.user {
+        settings.push(("USER", user.clone()));
+    }
+    if let Some(password) = &mail.password {
+        settings.push(("PASSWD", password.clone()));
+    }
+    settings
+}
+
+/// Returns the settings used to store the data of a section using `storage`.
+fn object_storage_settings(storage: &ObjectStorage) -> Vec<(&'static str, String)> {
+    vec![
+        ("STORAGE_TYPE", "minio".to_string()),
+        ("MINIO_ENDPOINT", storage.endpoint.clone()),
+        ("MINIO_BUCKET", storage.bucket.clone()),
+        ("MINIO_LOCATION", storage.region.clone()),
+        ("MINIO_ACCESS_KEY_ID", storage.access_key.clone()),
+        ("MINIO_SECRET_ACCESS_KEY", storage.secret_key.clone()),
+        ("MINIO_USE_SSL", storage.use_ssl.to_string()),
+    ]
+}
+
+/// Returns the `[indexer]` settings used to index code and issues using `indexer`.
+fn indexer_settings(indexer: &Indexer) -> Vec<(&'static str, String)> {
+    let mut settings = vec![("REPO_INDEXER_ENABLED", "true".to_string())];
+    match indexer {
+        Indexer::Bleve(_) => settings.extend([
+            ("REPO_INDEXER_TYPE", "bleve".to_string()),
+            ("REPO_INDEXER_PATH", format!("{INDEXER_PATH}/repos.bleve")),
+            ("ISSUE_INDEXER_TYPE", "bleve".to_string()),
+            ("ISSUE_INDEXER_PATH", format!("{INDEXER_PATH}/issues.bleve")),
+        ]),
+        Indexer::Elasticsearch(url) => settings.extend([
+            ("REPO_INDEXER_TYPE", "elasticsearch".to_string()),
+            ("REPO_INDEXER_CONN_STR", url.clone()),
+            ("ISSUE_INDEXER_TYPE", "elasticsearch".to_string()),
+            ("ISSUE_INDEXER_CONN_STR", url.clone()),
+        ]),
+    }
+    settings
+}
+
+/// Returns the name of the environment variable used by `environment-to-ini` to set `key` in
+/// `section`.
+fn app_ini_env(section: &str, key: &str) -> String {
+    let section = section.replace('.', "_0X2E_").replace('-', "_0X2D_");
+    format!("GITEA__{section}__{key}")
+}
 
 #[derive(Construct)]
 #[construct(builder)]
@@ -30,8 +183,9 @@ pub struct Gitea {
     path: String,
     #[construct(setter(into_value))]
     domain: Value<String>,
-    #[construct(setter(into_value))]
-    cache_host: Value<String>,
+    /// Cache backend. Sessions and queues are stored using Redis as well if selected.
+    #[construct(setter(into))]
+    cache: Cache,
     #[construct(setter(into_value))]
     db_host: Value<String>,
     #[construct(setter(into_value))]
@@ -48,17 +202,177 @@ pub struct Gitea {
     root_email: Value<String>,
     #[construct(setter(into_value))]
     volume_claim: Value<String>,
+    /// Additional `app.ini` settings grouped by section.
+    #[construct(setter(into))]
+    app_ini: HashMap<String, HashMap<String, String>>,
+    /// SMTP server used to send notifications. Mails are disabled if not set.
+    #[construct(setter(into))]
+    mail: Option<MailConfig>,
+    /// External authentication sources (LDAP or OpenID Connect).
+    #[construct(setter(into))]
+    auth_sources: Vec<AuthSource>,
+    /// S3-compatible buckets used to store data, grouped by `app.ini` section (see
+    /// [`OBJECT_STORAGE_SECTIONS`]). Data is stored on the volume if not set.
+    #[construct(setter(into))]
+    object_storage: HashMap<String, ObjectStorage>,
+    /// Backend used to index code and issues. Code search is disabled if not set.
+    #[construct(setter(into))]
+    indexer: Option<Indexer>,
+    /// Enables the package registry. The container registry will be served at `/v2` (see
+    /// [`Gitea::packages_ingress`]).
+    #[construct(setter(into))]
+    packages: bool,
+    /// Enables Gitea Actions. Runners are added using [`Gitea::runner`].
+    #[construct(setter(into))]
+    actions: bool,
+    /// Number of Gitea pods. Multiple replicas require a shared volume, Redis as cache and an
+    /// external indexer.
+    #[construct(setter(into))]
+    replicas: i64,
+    /// Whether `volume_claim` can be mounted by multiple nodes (`ReadWriteMany`).
+    #[construct(setter(into))]
+    shared_volume: bool,
+    /// Exposes metrics at `/metrics` scraped by Prometheus using `metrics_token`.
+    #[construct(setter(into))]
+    monitoring: Option<Monitoring>,
+    #[construct(setter(into))]
+    metrics_token: Option<String>,
+    #[construct(skip)]
+    credentials_ref: RefCell<Option<Value<String>>>,
 }
 
 impl Gitea {
+    /// Returns the external URL of the web interface.
+    pub fn url(&self) -> String {
+        format!("https://{}{}/", self.domain.get(), self.path)
+    }
+
+    /// Returns the in-cluster endpoint used to clone repositories via SSH.
+    pub fn ssh_url(&self) -> String {
+        format!(
+            "ssh://git@{}-service.{}.svc:{SSH_PORT}",
+            self.name,
+            self.namespace.get()
+        )
+    }
+
+    /// Returns the in-cluster URL of the web interface.
+    pub fn internal_url(&self) -> String {
+        format!(
+            "http://{}-service.{}.svc:3000",
+            self.name,
+            self.namespace.get()
+        )
+    }
+
+    /// Returns the in-cluster URL of the Gitea API.
+    pub fn api_url(&self) -> String {
+        format!("{}/api/v1", self.internal_url())
+    }
+
+    /// Returns a preconfigured builder for a job reconciling users, organisations, repositories
+    /// and teams of this instance. Will use `name` as name of the job.
+    pub fn bootstrap(self: &Rc<Self>, name: impl Into<String>) -> GiteaBootstrapBuilder {
+        let mut builder = GiteaBootstrap::create(self, name);
+        builder
+            .namespace(&self.namespace)
+            .api_url(self.api_url())
+            .credentials(self.credentials_ref.borrow().clone().unwrap());
+        builder
+    }
+
+    /// Returns a preconfigured builder for a pool of Gitea Actions runners registered at this
+    /// instance. Requires [`GiteaBuilder::actions`] to be enabled.
+    pub fn runner(self: &Rc<Self>, name: impl Into<String>) -> GiteaRunnerBuilder {
+        assert!(self.actions, "Gitea Actions are disabled");
+        let mut builder = GiteaRunner::create(self, name);
+        builder
+            .namespace(&self.namespace)
+            .instance_url(self.internal_url())
+            .api_url(self.api_url())
+            .root_user(&self.root_user)
+            .root_passwd(&self.root_passwd);
+        builder
+    }
+
+    /// Returns a preconfigured builder for a Woodpecker CI server using this instance to sign in
+    /// users. The server uses the root user to register its OAuth2 application.
+    pub fn woodpecker(self: &Rc<Self>, name: impl Into<String>) -> WoodpeckerBuilder {
+        let mut builder = Woodpecker::create(self, name);
+        builder
+            .namespace(&self.namespace)
+            .domain(self.domain.get().as_str())
+            .gitea_url(self.url())
+            .gitea_api_url(self.api_url())
+            .gitea_credentials(self.credentials_ref.borrow().clone().unwrap())
+            .admin(&self.root_user);
+        builder
+    }
+
+    /// Returns a preconfigured builder for Grafana using this instance to sign in users. The root
+    /// user is granted the `Admin` role and used to register the OAuth2 application.
+    pub fn grafana(self: &Rc<Self>, name: impl Into<String>) -> GrafanaBuilder {
+        let mut builder = Grafana::create(self, name);
+        builder
+            .namespace(&self.namespace)
+            .domain(self.domain.get().as_str())
+            .gitea_url(self.url())
+            .gitea_internal_url(self.internal_url())
+            .gitea_api_url(self.api_url())
+            .gitea_credentials(self.credentials_ref.borrow().clone().unwrap())
+            .admin(&self.root_user);
+        builder
+    }
+
+    /// Returns a human readable reference to the secret storing the root user credentials.
+    pub fn credentials(&self) -> String {
+        format!("secret {}/{}", self.namespace.get(), self.name)
+    }
+
+    /// Returns the web interface and SSH server as endpoint of network policies.
+    pub fn endpoint(&self) -> NetworkEndpoint {
+        NetworkEndpoint::app(format!("gitea-{}", self.name), [3000, SSH_PORT])
+    }
+
+    /// Returns the Gitea pods as source of network policies.
+    pub fn peer(&self) -> NetworkPeer {
+        NetworkPeer::app(format!("gitea-{}", self.name))
+    }
+
     pub fn ingress(&self) -> IngressServiceConfig {
         IngressServiceConfig {
             rewrite: true,
             path: self.path.clone(),
             service_name: format!("{}-service", self.name),
             service_port: 3000,
+            pod_labels: self.endpoint().pod_labels,
+            routed_paths: Vec::new(),
+            blocked_paths: Vec::new(),
         }
     }
+
+    /// Returns the ingress configuration of the package registries if packages are enabled.
+    /// Container clients expect the registry at `/v2` of the domain, so the path will be passed
+    /// to Gitea unchanged.
+    pub fn packages_ingress(&self) -> Vec<IngressServiceConfig> {
+        if !self.packages {
+            return Vec::new();
+        }
+        let registry = IngressServiceConfig {
+            rewrite: false,
+            path: "/v2".to_string(),
+            service_name: format!("{}-service", self.name),
+            service_port: 3000,
+            pod_labels: self.endpoint().pod_labels,
+            routed_paths: Vec::new(),
+            blocked_paths: Vec::new(),
+        };
+        let api = IngressServiceConfig {
+            routed_paths: vec!["/api/packages".to_string()],
+            ..self.ingress()
+        };
+        vec![registry, api]
+    }
 }
 
 impl GiteaBuilder {
@@ -69,7 +383,7 @@ impl GiteaBuilder {
             namespace: self.namespace.clone().expect("missing field 'namespace'"),
             path: self.path.clone().unwrap_or("/".into()),
             domain: self.domain.clone().unwrap_or("localhost".into_value()),
-            cache_host: self.cache_host.clone().unwrap_or("localhost".into_value()),
+            cache: self.cache.clone().unwrap_or_default(),
             db_host: self.db_host.clone().unwrap_or("localhost".into_value()),
             db_name: self.db_name.clone().expect("missing field 'db_name'"),
             db_user: self.db_user.clone().expect("missing field 'db_user'"),
@@ -87,8 +401,45 @@ impl GiteaBuilder {
                 .volume_claim
                 .clone()
                 .expect("missing field 'volume_claim'"),
+            app_ini: self.app_ini.clone().unwrap_or_default(),
+            mail: self.mail.clone().flatten(),
+            auth_sources: self.auth_sources.clone().unwrap_or_default(),
+            object_storage: self.object_storage.clone().unwrap_or_default(),
+            indexer: self.indexer.clone().flatten(),
+            packages: self.packages.unwrap_or(false),
+            actions: self.actions.unwrap_or(false),
+            replicas: self.replicas.unwrap_or(1),
+            shared_volume: self.shared_volume.unwrap_or(false),
+            monitoring: self.monitoring.clone().flatten(),
+            metrics_token: self.metrics_token.clone().flatten(),
+            credentials_ref: RefCell::new(None),
         });
 
+        if this.replicas > 1 {
+            assert!(
+                this.shared_volume,
+                "multiple Gitea replicas require a shared (ReadWriteMany) volume"
+            );
+            assert!(
+                matches!(this.cache, Cache::Redis(_)),
+                "multiple Gitea replicas require Redis to share sessions and queues"
+            );
+            assert!(
+                !matches!(this.indexer, Some(Indexer::Bleve(_))),
+                "multiple Gitea replicas can not share a bleve index"
+            );
+        }
+        assert!(
+            this.monitoring.is_none() || this.metrics_token.is_some(),
+            "monitoring requires a metrics token"
+        );
+        for section in this.object_storage.keys() {
+            assert!(
+                OBJECT_STORAGE_SECTIONS.contains(&section.as_str()),
+                "app.ini section '{section}' does not support object storage"
+            );
+        }
+
         let name = &this.name;
         let labels = crate::map! {
             "app" = format!("gitea-{name}")
@@ -106,6 +457,10 @@ impl GiteaBuilder {
                         name = "web"
                         port = 3000
                     }
+                    port {
+                        name = "ssh"
+                        port = SSH_PORT
+                    }
                 }
             }
         };
@@ -124,27 +479,107 @@ impl GiteaBuilder {
                 }
             }
         };
+        this.credentials_ref
+            .replace(Some((&init_root_config.metadata[0].name).into_value()));
+
+        // Cron tasks are only run by the first replica. Other replicas disable cron as a whole
+        // and every task configured explicitly, since tasks may override the global setting.
+        let mut cron_tasks: Vec<_> = this
+            .app_ini
+            .keys()
+            .filter_map(|section| section.strip_prefix("cron."))
+            .collect();
+        cron_tasks.sort();
+
+        let mut settings: HashMap<String, Value<String>> = HashMap::new();
+        let mut secret_settings: HashMap<String, Value<String>> = HashMap::new();
+        for (section, values) in &this.app_ini {
+            for (key, value) in values {
+                assert!(
+                    !is_managed_setting(section, key),
+                    "app.ini setting '{section}.{key}' is managed by the Gitea construct"
+                );
+                let env = app_ini_env(section, key);
+                match is_secret_setting(key) {
+                    true => secret_settings.insert(env, value.into_value()),
+                    false => settings.insert(env, value.into_value()),
+                };
+            }
+        }
+        let mut managed: Vec<_> = this
+            .mail
+            .iter()
+            .flat_map(|mail| {
+                mailer_settings(mail)
+                    .into_iter()
+                    .map(|(key, value)| ("mailer", key, value))
+            })
+            .collect();
+        for (key, value) in this.indexer.iter().flat_map(indexer_settings) {
+            managed.push(("indexer", key, value));
+        }
+        if this.indexer.is_none() && this.replicas > 1 {
+            // The default bleve issue index can not be shared by multiple replicas.
+            managed.push(("indexer", "ISSUE_INDEXER_TYPE", "db".to_string()));
+        }
+        if let Some(token) = &this.metrics_token {
+            managed.push(("metrics", "ENABLED", "true".to_string()));
+            managed.push(("metrics", "TOKEN", token.clone()));
+        }
+        for (section, storage) in &this.object_storage {
+            for (key, value) in object_storage_settings(storage) {
+                managed.push((section.as_str(), key, value));
+            }
+        }
+        for (section, key, value) in managed {
+            let env = app_ini_env(section, key);
+            match is_secret_setting(key) {
+                true => secret_settings.insert(env, value.into_value()),
+                false => settings.insert(env, value.into_value()),
+            };
+        }
+        // Cache settings may contain the Redis password.
+        for (section, key, value) in this.cache.settings() {
+            secret_settings.insert(app_ini_env(section, key), value.into_value());
+        }
+        let auth_sources = auth_script(&this.auth_sources);
+        let mut checksum: Vec<_> = settings.iter().chain(&secret_settings).collect();
+        checksum.sort_by_key(|(key, _)| *key);
+        let checksum = crate::helper::checksum((checksum, &auth_sources));
+
+        let app_ini_secret = resource! {
+            &this, resource "kubernetes_secret" "gitea-app-ini" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-app-ini")
+                }
+                data = secret_settings
+            }
+        };
+
+        let mut config_data = crate::map! {
+            "USER_GID" = "1000",
+            "USER_UID" = "1000",
+            "GITEA_WORK_DIR" = "/gitea",
+            "GITEA_CUSTOM" = "/gitea/custom",
+            "GITEA_APP_INI" = "/gitea/custom/conf/app.ini",
+            "GITEA__database__DB_TYPE" = "postgres",
+            "GITEA__database__HOST" = &this.db_host,
+            "GITEA__database__NAME" = &this.db_name,
+            "GITEA__database__USER" = &this.db_user,
+            "GITEA__database__PASSWD" = &this.db_password,
+            "GITEA__server__ROOT_URL" = this.url(),
+            "GITEA__packages__ENABLED" = this.packages.to_string(),
+            "GITEA__actions__ENABLED" = this.actions.to_string()
+        };
+        config_data.extend(settings);
         let config = resource! {
             &this, resource "kubernetes_config_map" "gitea-config" {
                 metadata {
                     namespace = &this.namespace
                     name = name
                 }
-                data = crate::map!{
-                    "USER_GID" = "1000",
-                    "USER_UID" = "1000",
-                    "GITEA_WORK_DIR" = "/gitea",
-                    "GITEA_CUSTOM" = "/gitea/custom",
-                    "GITEA_APP_INI" = "/gitea/custom/conf/app.ini",
-                    "GITEA__database__DB_TYPE" = "postgres",
-                    "GITEA__database__HOST" = &this.db_host,
-                    "GITEA__database__NAME" = &this.db_name,
-                    "GITEA__database__USER" = &this.db_user,
-                    "GITEA__database__PASSWD" = &this.db_password,
-                    "GITEA__server__ROOT_URL" = format!("https://{}{}/", this.domain.get(), this.path),
-                    "GITEA__cache__ADAPTER" = "memcache",
-                    "GITEA__cache__HOST" = &this.cache_host
-                }
+                data = config_data
             }
         };
         let init_config = resource! {
@@ -156,11 +591,43 @@ impl GiteaBuilder {
                 }
                 data = crate::map! {
                     "init.sh" = INIT_SCRIPT,
-                    "migrate.sh" = MIGRATION_SCRIPT
+                    "migrate.sh" = MIGRATION_SCRIPT,
+                    "auth.sh" = AUTH_SCRIPT
+                }
+            }
+        };
+        let auth_config = resource! {
+            &this, resource "kubernetes_secret" "gitea-auth-sources" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-auth-sources")
+                }
+                data = crate::map! {
+                    "auth-sources.sh" = auth_sources
                 }
             }
         };
 
+        // The bleve index is stored on a dedicated volume mounted inside the data directory.
+        let (index_volumes, index_mounts) = match &this.indexer {
+            Some(Indexer::Bleve(volume_claim)) => {
+                let claim =
+                    KubernetesStatefulSetSpecTemplateSpecVolumePersistentVolumeClaim::builder()
+                        .claim_name(volume_claim)
+                        .build();
+                let volume = KubernetesStatefulSetSpecTemplateSpecVolume::builder()
+                    .name("indexers")
+                    .persistent_volume_claim(claim)
+                    .build();
+                let mount = KubernetesStatefulSetSpecTemplateSpecContainerVolumeMount::builder()
+                    .name("indexers")
+                    .mount_path(INDEXER_PATH)
+                    .build();
+                (vec![volume], vec![mount])
+            }
+            _ => (vec![], vec![]),
+        };
+
         resource! {
             &this, resource "kubernetes_stateful_set" "gitea" {
                 metadata {
@@ -168,7 +635,7 @@ impl GiteaBuilder {
                     name = name
                 }
                 spec {
-                    replicas = "1"
+                    replicas = this.replicas.to_string()
                     service_name = &service.metadata[0].name
                     selector {
                         match_labels = &labels
@@ -176,11 +643,15 @@ impl GiteaBuilder {
                     template {
                         metadata {
                             labels = &labels
+                            annotations = crate::map! {
+                                "gitserver/checksum" = checksum
+                            }
                         }
                         spec {
+                            security_context = security::stateful_set::pod(1000, 1000)
                             init_container {
                                 name = "init"
-                                image = "gitea/gitea:1.19.0-rootless"
+                                image = GITEA_IMAGE
                                 command = ["bash", "/usr/sbin/init.sh"]
                                 volume_mount {
                                     name = "giteadata"
@@ -195,13 +666,11 @@ impl GiteaBuilder {
                                     name = "init-scripts"
                                     mount_path = "/usr/sbin"
                                 }
-                                security_context {
-                                    run_as_user = "0"
-                                }
+                                security_context = security::stateful_set::init_container()
                             }
                             init_container {
                                 name = "init-gitea"
-                                image = "gitea/gitea:1.19.0-rootless"
+                                image = GITEA_IMAGE
                                 command = ["bash", "/usr/sbin/migrate.sh"]
                                 volume_mount {
                                     name = "giteadata"
@@ -217,31 +686,79 @@ impl GiteaBuilder {
                                         name = &init_root_config.metadata[0].name
                                     }
                                 }
+                                env_from {
+                                    secret_ref {
+                                        name = &app_ini_secret.metadata[0].name
+                                    }
+                                }
                                 volume_mount {
                                     name = "init-scripts"
                                     mount_path = "/usr/sbin"
                                 }
+                                volume_mount {
+                                    name = "auth-sources"
+                                    mount_path = "/etc/gitea-auth"
+                                }
+                                volume_mount {
+                                    name = "pod-config"
+                                    mount_path = "/etc/gitea"
+                                }
+                                env {
+                                    name = "AUTH_SOURCES"
+                                    value = "/etc/gitea-auth/auth-sources.sh"
+                                }
+                                env {
+                                    name = "POD_APP_INI"
+                                    value = POD_APP_INI
+                                }
+                                env {
+                                    name = "CRON_TASKS"
+                                    value = cron_tasks.join(" ")
+                                }
+                                volume_mount {
+                                    name = "tmp"
+                                    mount_path = "/tmp"
+                                }
+                                security_context = security::stateful_set::init_container()
                             }
                             container {
                                 name = "gitea"
-                                image = "gitea/gitea:1.19.0-rootless"
+                                image = GITEA_IMAGE
                                 port {
                                     name = "http"
                                     container_port = 3000
                                 }
                                 port {
                                     name = "ssh"
-                                    container_port = 22
+                                    container_port = SSH_PORT
                                 }
                                 volume_mount {
                                     name = "giteadata"
                                     mount_path = "/gitea"
                                 }
+                                volume_mount = index_mounts
+                                volume_mount {
+                                    name = "pod-config"
+                                    mount_path = "/etc/gitea"
+                                }
+                                volume_mount {
+                                    name = "tmp"
+                                    mount_path = "/tmp"
+                                }
+                                env {
+                                    name = "GITEA_APP_INI"
+                                    value = POD_APP_INI
+                                }
                                 env_from {
                                     config_map_ref {
                                         name = &config.metadata[0].name
                                     }
                                 }
+                                env_from {
+                                    secret_ref {
+                                        name = &app_ini_secret.metadata[0].name
+                                    }
+                                }
                                 readiness_probe {
                                     http_get {
                                         path = "/api/healthz"
@@ -258,6 +775,7 @@ impl GiteaBuilder {
                                     period_seconds = 12
                                     timeout_seconds = 5
                                 }
+                                security_context = security::stateful_set::container()
                             }
                             volume {
                                 name = "giteadata"
@@ -271,11 +789,71 @@ impl GiteaBuilder {
                                     secret_name = &init_config.metadata[0].name
                                 }
                             }
+                            volume {
+                                name = "auth-sources"
+                                secret {
+                                    secret_name = &auth_config.metadata[0].name
+                                }
+                            }
+                            volume {
+                                name = "pod-config"
+                                empty_dir {}
+                            }
+                            volume {
+                                name = "tmp"
+                                empty_dir {}
+                            }
+                            volume = index_volumes
+                            affinity {
+                                pod_anti_affinity {
+                                    preferred_during_scheduling_ignored_during_execution {
+                                        weight = 100
+                                        pod_affinity_term {
+                                            topology_key = "kubernetes.io/hostname"
+                                            label_selector {
+                                                match_labels = &labels
+                                            }
+                                        }
+                                    }
+                                }
+                            }
                         }
                     }
                 }
             }
         };
+
+        if let (Some(monitoring), Some(token)) = (&this.monitoring, &this.metrics_token) {
+            PodMonitor::create(&this, format!("{name}-metrics"))
+                .namespace(&this.namespace)
+                .monitoring(monitoring.clone())
+                .pod_labels([("app".to_string(), format!("gitea-{name}"))])
+                .port("http")
+                .metrics_port(3000)
+                .token(MetricsToken {
+                    secret_name: format!("{name}-app-ini"),
+                    key: app_ini_env("metrics", "TOKEN"),
+                    value: token.clone(),
+                })
+                .build();
+        }
+
+        if this.replicas > 1 {
+            resource! {
+                &this, resource "kubernetes_pod_disruption_budget_v1" "gitea" {
+                    metadata {
+                        namespace = &this.namespace
+                        name = name
+                    }
+                    spec {
+                        max_unavailable = "1"
+                        selector {
+                            match_labels = &labels
+                        }
+                    }
+                }
+            };
+        }
         this
     }
 }
@@ -0,0 +1,141 @@
+/// External authentication source registered in Gitea during bootstrap.
+#[derive(Clone)]
+pub enum AuthSource {
+    Ldap(LdapSource),
+    OpenIdConnect(OidcSource),
+}
+
+/// Security protocol used to connect to a LDAP server.
+#[derive(Clone, Copy)]
+pub enum LdapSecurity {
+    Unencrypted,
+    Ldaps,
+    StartTls,
+}
+
+/// LDAP server authenticating users using a bind DN.
+#[derive(Clone)]
+pub struct LdapSource {
+    pub name: String,
+    pub host: String,
+    pub port: i64,
+    pub security: LdapSecurity,
+    pub bind_dn: String,
+    pub bind_password: String,
+    pub user_search_base: String,
+    pub user_filter: String,
+    pub admin_filter: Option<String>,
+    pub username_attribute: String,
+    pub firstname_attribute: Option<String>,
+    pub surname_attribute: Option<String>,
+    pub email_attribute: String,
+}
+
+/// OpenID Connect provider using auto discovery.
+#[derive(Clone)]
+pub struct OidcSource {
+    pub name: String,
+    pub discovery_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scopes: Vec<String>,
+    /// Name of the claim containing the groups of a user.
+    pub group_claim: Option<String>,
+    /// Members of this group will be Gitea administrators.
+    pub admin_group: Option<String>,
+    /// JSON document mapping groups onto organisation teams, e.g.
+    /// `{"developers": {"infra": ["maintainers"]}}`.
+    pub group_team_map: Option<String>,
+}
+
+impl AuthSource {
+    pub fn name(&self) -> &str {
+        match self {
+            AuthSource::Ldap(source) => &source.name,
+            AuthSource::OpenIdConnect(source) => &source.name,
+        }
+    }
+
+    /// Returns the shell command used to register or update this source. The command uses the
+    /// `auth_source` function defined in `script/gitea/auth.sh`.
+    pub fn to_command(&self) -> String {
+        let mut args = vec![];
+        let kind = match self {
+            AuthSource::Ldap(source) => {
+                let security = match source.security {
+                    LdapSecurity::Unencrypted => "Unencrypted",
+                    LdapSecurity::Ldaps => "LDAPS",
+                    LdapSecurity::StartTls => "StartTLS",
+                };
+                args.extend([
+                    ("--security-protocol", security.to_string()),
+                    ("--host", source.host.clone()),
+                    ("--port", source.port.to_string()),
+                    ("--bind-dn", source.bind_dn.clone()),
+                    ("--bind-password", source.bind_password.clone()),
+                    ("--user-search-base", source.user_search_base.clone()),
+                    ("--user-filter", source.user_filter.clone()),
+                    ("--username-attribute", source.username_attribute.clone()),
+                    ("--email-attribute", source.email_attribute.clone()),
+                ]);
+                let optional = [
+                    ("--admin-filter", &source.admin_filter),
+                    ("--firstname-attribute", &source.firstname_attribute),
+                    ("--surname-attribute", &source.surname_attribute),
+                ];
+                args.extend(
+                    optional
+                        .into_iter()
+                        .filter_map(|(flag, value)| Some((flag, value.clone()?))),
+                );
+                "ldap"
+            }
+            AuthSource::OpenIdConnect(source) => {
+                args.extend([
+                    ("--provider", "openidConnect".to_string()),
+                    ("--auto-discover-url", source.discovery_url.clone()),
+                    ("--key", source.client_id.clone()),
+                    ("--secret", source.client_secret.clone()),
+                ]);
+                if !source.scopes.is_empty() {
+                    args.push(("--scopes", source.scopes.join(",")));
+                }
+                let optional = [
+                    ("--group-claim-name", &source.group_claim),
+                    ("--admin-group", &source.admin_group),
+                    ("--group-team-map", &source.group_team_map),
+                ];
+                args.extend(
+                    optional
+                        .into_iter()
+                        .filter_map(|(flag, value)| Some((flag, value.clone()?))),
+                );
+                "oauth"
+            }
+        };
+        let args: Vec<_> = args
+            .into_iter()
+            .map(|(flag, value)| format!("{flag} {}", shell_quote(&value)))
+            .collect();
+        format!(
+            "auth_source {kind} {} {}",
+            shell_quote(self.name()),
+            args.join(" ")
+        )
+    }
+}
+
+/// Returns a shell script registering all `sources`.
+pub fn auth_script(sources: &[AuthSource]) -> String {
+    sources
+        .iter()
+        .map(AuthSource::to_command)
+        .fold(String::from("#!/usr/bin/env bash\n"), |script, command| {
+            script + &command + "\n"
+        })
+}
+
+/// Quotes `value` to be used as a single shell word.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r#"'"'"'"#))
+}
@@ -0,0 +1,181 @@
+use std::rc::Rc;
+
+use tf_bindgen::codegen::{resource, Construct};
+use tf_bindgen::value::Value;
+use tf_bindgen::Scope;
+use tf_kubernetes::kubernetes::resource::{kubernetes_job, kubernetes_secret};
+
+use super::network_policy::NetworkPeer;
+use super::security;
+
+const RECONCILE_SCRIPT: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/script/gitea/reconcile.py"
+));
+const GITEA_API_SCRIPT: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/script/gitea/gitea_api.py"
+));
+
+/// Job used to reconcile users, organisations, repositories, teams and mirrors of a Gitea instance
+/// using the Gitea API. The desired state is passed as JSON document (see
+/// `script/gitea/reconcile.py`).
+#[derive(Construct)]
+#[construct(builder)]
+#[allow(dead_code)]
+pub struct GiteaBootstrap {
+    #[construct(id)]
+    name: String,
+    #[construct(scope)]
+    scope: Rc<dyn Scope>,
+    #[construct(setter(into_value))]
+    namespace: Value<String>,
+    #[construct(setter(into_value))]
+    api_url: Value<String>,
+    #[construct(setter(into_value))]
+    credentials: Value<String>,
+    #[construct(setter(into))]
+    state: String,
+    /// Credentials of the mirror sources as JSON document keyed by `owner/name`.
+    #[construct(setter(into))]
+    mirror_credentials: String,
+    /// Identifier of the current run. Changing it replaces the job, so every apply detects and
+    /// reverts changes made by hand.
+    #[construct(setter(into))]
+    run_id: String,
+}
+
+impl GiteaBootstrap {
+    /// Returns the pods of the job and of the import jobs derived from it (see `gitserver
+    /// import`) as source of network policies.
+    pub fn peer(&self) -> NetworkPeer {
+        NetworkPeer::app(&self.name)
+    }
+}
+
+impl GiteaBootstrapBuilder {
+    pub fn build(&mut self) -> Rc<GiteaBootstrap> {
+        let this = Rc::new(GiteaBootstrap {
+            name: self.name.clone(),
+            scope: self.scope.clone(),
+            namespace: self.namespace.clone().expect("missing field 'namespace'"),
+            api_url: self.api_url.clone().expect("missing field 'api_url'"),
+            credentials: self
+                .credentials
+                .clone()
+                .expect("missing field 'credentials'"),
+            state: self.state.clone().unwrap_or("{}".into()),
+            mirror_credentials: self.mirror_credentials.clone().unwrap_or("{}".into()),
+            run_id: self.run_id.clone().unwrap_or_default(),
+        });
+
+        let name = &this.name;
+        // Jobs are immutable. Changing the checksum will force Terraform to replace the job.
+        let checksum = crate::helper::checksum((
+            RECONCILE_SCRIPT,
+            GITEA_API_SCRIPT,
+            &this.state,
+            &this.mirror_credentials,
+            &this.run_id,
+        ));
+
+        let config = resource! {
+            &this, resource "kubernetes_secret" "bootstrap-config" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-config")
+                }
+                data = crate::map! {
+                    "reconcile.py" = RECONCILE_SCRIPT,
+                    "gitea_api.py" = GITEA_API_SCRIPT,
+                    "state.json" = &this.state
+                }
+            }
+        };
+
+        let mirror_credentials = resource! {
+            &this, resource "kubernetes_secret" "bootstrap-mirror-credentials" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-mirror-credentials")
+                }
+                data = crate::map! {
+                    "credentials.json" = &this.mirror_credentials
+                }
+            }
+        };
+
+        resource! {
+            &this, resource "kubernetes_job" "bootstrap" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+                spec {
+                    // Failed runs are reported by `apply` (see `Kubectl::wait_for_job`).
+                    backoff_limit = 2
+                    template {
+                        metadata {
+                            labels = crate::map! {
+                                "app" = name
+                            }
+                            annotations = crate::map! {
+                                "gitserver/checksum" = checksum
+                            }
+                        }
+                        spec {
+                            restart_policy = "OnFailure"
+                            security_context = security::job::pod(1000, 1000)
+                            container {
+                                name = "reconcile"
+                                image = "python:3.11-alpine"
+                                command = ["python3", "/config/reconcile.py"]
+                                env {
+                                    name = "GITEA_API_URL"
+                                    value = &this.api_url
+                                }
+                                env {
+                                    name = "STATE_FILE"
+                                    value = "/config/state.json"
+                                }
+                                env {
+                                    name = "CREDENTIALS_FILE"
+                                    value = "/credentials/credentials.json"
+                                }
+                                env_from {
+                                    secret_ref {
+                                        name = &this.credentials
+                                    }
+                                }
+                                volume_mount {
+                                    name = "config"
+                                    mount_path = "/config"
+                                }
+                                volume_mount {
+                                    name = "credentials"
+                                    mount_path = "/credentials"
+                                }
+                                security_context = security::job::container()
+                            }
+                            volume {
+                                name = "config"
+                                secret {
+                                    secret_name = &config.metadata[0].name
+                                }
+                            }
+                            volume {
+                                name = "credentials"
+                                secret {
+                                    secret_name = &mirror_credentials.metadata[0].name
+                                }
+                            }
+                        }
+                    }
+                }
+                wait_for_completion = false
+            }
+        };
+
+        this
+    }
+}
@@ -0,0 +1,314 @@
+use std::rc::Rc;
+
+use tf_bindgen::codegen::{resource, Construct};
+use tf_bindgen::json;
+use tf_bindgen::value::Value;
+use tf_bindgen::Scope;
+use tf_kubernetes::kubernetes::resource::kubernetes_secret;
+use tf_kubernetes::kubernetes::resource::kubernetes_stateful_set::{
+    self, KubernetesStatefulSetSpecVolumeClaimTemplate,
+    KubernetesStatefulSetSpecVolumeClaimTemplateMetadata,
+    KubernetesStatefulSetSpecVolumeClaimTemplateSpec,
+    KubernetesStatefulSetSpecVolumeClaimTemplateSpecResources,
+};
+
+use super::dind;
+use super::network_policy::NetworkPeer;
+use super::security;
+
+const TOKEN_SCRIPT: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/script/gitea/runner-token.sh"
+));
+
+const RUNNER_IMAGE: &str = "gitea/act_runner:0.2.6";
+
+/// Environment used to execute the jobs of a runner.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RunnerExecutor {
+    /// Runs every job in a separate container using a privileged Docker-in-Docker sidecar.
+    Dind,
+    /// Runs jobs directly inside the runner pods (`host` executor of act_runner). Does not
+    /// require privileged containers, but jobs can only use the tools available in the runner
+    /// image.
+    Host,
+}
+
+impl RunnerExecutor {
+    fn default_labels(&self) -> Vec<String> {
+        let labels: &[&str] = match self {
+            RunnerExecutor::Dind => &[
+                "ubuntu-latest:docker://node:16-bullseye",
+                "ubuntu-22.04:docker://node:16-bullseye",
+            ],
+            RunnerExecutor::Host => &["host:host"],
+        };
+        labels.iter().map(|label| label.to_string()).collect()
+    }
+}
+
+/// Pool of [act_runner](https://gitea.com/gitea/act_runner) instances executing Gitea Actions.
+/// Every runner registers itself using a token obtained from the Gitea API on its first start.
+/// The registration is stored on a volume of each runner, so restarted runners keep their
+/// identity.
+#[derive(Construct)]
+#[construct(builder)]
+#[allow(dead_code)]
+pub struct GiteaRunner {
+    #[construct(id)]
+    name: String,
+    #[construct(scope)]
+    scope: Rc<dyn Scope>,
+    #[construct(setter(into_value))]
+    namespace: Value<String>,
+    /// URL of the Gitea instance the runners register at.
+    #[construct(setter(into_value))]
+    instance_url: Value<String>,
+    #[construct(setter(into_value))]
+    api_url: Value<String>,
+    /// Credentials of the Gitea root user used to obtain registration tokens. Stored in a
+    /// secret of `namespace`, since runners may use another namespace than Gitea.
+    #[construct(setter(into_value))]
+    root_user: Value<String>,
+    #[construct(setter(into_value))]
+    root_passwd: Value<String>,
+    /// Number of runner pods.
+    #[construct(setter(into))]
+    replicas: i64,
+    /// Number of jobs executed concurrently by a single runner.
+    #[construct(setter(into))]
+    capacity: i64,
+    /// Labels of the runners, e.g. `ubuntu-latest:docker://node:16-bullseye`. Uses labels
+    /// matching the executor if empty.
+    #[construct(setter(into))]
+    labels: Vec<String>,
+    #[construct(setter(into))]
+    executor: RunnerExecutor,
+    /// Storage class provisioning the volume of each runner. Uses the default storage class of
+    /// the cluster if not set.
+    #[construct(setter(into))]
+    storage_class: Option<String>,
+    #[construct(setter(into))]
+    storage: String,
+}
+
+impl GiteaRunner {
+    /// Returns the runner pods as source of network policies.
+    pub fn peer(&self) -> NetworkPeer {
+        NetworkPeer::app(format!("runner-{}", self.name))
+            .in_namespace(self.namespace.get().as_str())
+    }
+}
+
+impl GiteaRunnerBuilder {
+    pub fn build(&mut self) -> Rc<GiteaRunner> {
+        let this = Rc::new(GiteaRunner {
+            name: self.name.clone(),
+            scope: self.scope.clone(),
+            namespace: self.namespace.clone().expect("missing field 'namespace'"),
+            instance_url: self
+                .instance_url
+                .clone()
+                .expect("missing field 'instance_url'"),
+            api_url: self.api_url.clone().expect("missing field 'api_url'"),
+            root_user: self.root_user.clone().expect("missing field 'root_user'"),
+            root_passwd: self
+                .root_passwd
+                .clone()
+                .expect("missing field 'root_passwd'"),
+            replicas: self.replicas.unwrap_or(1),
+            capacity: self.capacity.unwrap_or(1),
+            labels: self.labels.clone().unwrap_or_default(),
+            executor: self.executor.unwrap_or(RunnerExecutor::Dind),
+            storage_class: self.storage_class.clone().flatten(),
+            storage: self.storage.clone().unwrap_or("1Gi".into()),
+        });
+
+        let name = &this.name;
+        let labels = crate::map! {
+            "app" = format!("runner-{name}")
+        };
+
+        let runner_labels = match this.labels.is_empty() {
+            true => this.executor.default_labels(),
+            false => this.labels.clone(),
+        };
+        let runner_config = json::json!({
+            "log": { "level": "info" },
+            "runner": {
+                "file": ".runner",
+                "capacity": this.capacity,
+                "labels": runner_labels
+            },
+            "container": { "privileged": false }
+        });
+        let runner_config = crate::helper::yaml(&runner_config);
+        let checksum = crate::helper::checksum((&runner_config, TOKEN_SCRIPT));
+
+        let config = resource! {
+            &this, resource "kubernetes_secret" "runner-config" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-config")
+                }
+                data = crate::map! {
+                    "config.yaml" = runner_config,
+                    "runner-token.sh" = TOKEN_SCRIPT
+                }
+            }
+        };
+
+        let credentials = resource! {
+            &this, resource "kubernetes_secret" "runner-credentials" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-credentials")
+                }
+                data = crate::map! {
+                    "ROOT_USER" = &this.root_user,
+                    "ROOT_PASSWD" = &this.root_passwd
+                }
+            }
+        };
+
+        let (docker_env, sidecars) = match this.executor {
+            RunnerExecutor::Dind => (
+                dind::stateful_set::docker_env(),
+                vec![dind::stateful_set::sidecar()],
+            ),
+            RunnerExecutor::Host => (vec![], vec![]),
+        };
+        let mut data_spec = KubernetesStatefulSetSpecVolumeClaimTemplateSpec::builder();
+        data_spec.access_modes(["ReadWriteOnce"]).resources(
+            KubernetesStatefulSetSpecVolumeClaimTemplateSpecResources::builder()
+                .requests(crate::map! {
+                    "storage" = &this.storage
+                })
+                .build(),
+        );
+        if let Some(storage_class) = &this.storage_class {
+            data_spec.storage_class_name(storage_class);
+        }
+        let data_claim = KubernetesStatefulSetSpecVolumeClaimTemplate::builder()
+            .metadata(
+                KubernetesStatefulSetSpecVolumeClaimTemplateMetadata::builder()
+                    .name("data")
+                    .build(),
+            )
+            .spec(data_spec.build())
+            .build();
+
+        // Runners are named after their pod, which is stable within a stateful set.
+        resource! {
+            &this, resource "kubernetes_stateful_set" "runner" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+                spec {
+                    replicas = this.replicas.to_string()
+                    service_name = name
+                    selector {
+                        match_labels = &labels
+                    }
+                    template {
+                        metadata {
+                            labels = &labels
+                            annotations = crate::map! {
+                                "gitserver/checksum" = checksum
+                            }
+                        }
+                        spec {
+                            security_context = security::stateful_set::pod(1000, 1000)
+                            init_container {
+                                name = "registration-token"
+                                image = RUNNER_IMAGE
+                                command = ["sh", "/config/runner-token.sh"]
+                                env {
+                                    name = "GITEA_API_URL"
+                                    value = &this.api_url
+                                }
+                                env {
+                                    name = "TOKEN_FILE"
+                                    value = "/data/token"
+                                }
+                                env_from {
+                                    secret_ref {
+                                        name = &credentials.metadata[0].name
+                                    }
+                                }
+                                volume_mount {
+                                    name = "data"
+                                    mount_path = "/data"
+                                }
+                                volume_mount {
+                                    name = "config"
+                                    mount_path = "/config"
+                                }
+                                security_context = security::stateful_set::init_container()
+                            }
+                            container {
+                                name = "runner"
+                                image = RUNNER_IMAGE
+                                env {
+                                    name = "GITEA_INSTANCE_URL"
+                                    value = &this.instance_url
+                                }
+                                env {
+                                    name = "GITEA_RUNNER_REGISTRATION_TOKEN_FILE"
+                                    value = "/data/token"
+                                }
+                                env {
+                                    name = "GITEA_RUNNER_NAME"
+                                    value_from {
+                                        field_ref {
+                                            field_path = "metadata.name"
+                                        }
+                                    }
+                                }
+                                env {
+                                    name = "CONFIG_FILE"
+                                    value = "/config/config.yaml"
+                                }
+                                // Jobs of the host executor are run inside the home directory,
+                                // which is not persisted.
+                                env {
+                                    name = "HOME"
+                                    value = "/tmp"
+                                }
+                                env = docker_env
+                                volume_mount {
+                                    name = "data"
+                                    mount_path = "/data"
+                                }
+                                volume_mount {
+                                    name = "config"
+                                    mount_path = "/config"
+                                }
+                                volume_mount {
+                                    name = "tmp"
+                                    mount_path = "/tmp"
+                                }
+                                security_context = security::stateful_set::container()
+                            }
+                            container = sidecars
+                            volume {
+                                name = "tmp"
+                                empty_dir {}
+                            }
+                            volume {
+                                name = "config"
+                                secret {
+                                    secret_name = &config.metadata[0].name
+                                }
+                            }
+                        }
+                    }
+                    volume_claim_template = vec![data_claim]
+                }
+            }
+        };
+
+        this
+    }
+}
@@ -0,0 +1,489 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use tf_bindgen::codegen::{resource, Construct};
+use tf_bindgen::json;
+use tf_bindgen::value::{IntoValue, Value};
+use tf_bindgen::Scope;
+use tf_kubernetes::kubernetes::resource::{
+    kubernetes_config_map, kubernetes_deployment, kubernetes_secret, kubernetes_service,
+};
+
+use super::ingress::IngressServiceConfig;
+use super::network_policy::{NetworkEndpoint, NetworkPeer};
+use super::security;
+
+const OAUTH_SCRIPT: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/script/gitea/oauth-app.py"
+));
+const GITEA_API_SCRIPT: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/script/gitea/gitea_api.py"
+));
+
+const DASHBOARDS: [(&str, &str); 4] = [
+    (
+        "gitea.json",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/dashboards/gitea.json"
+        )),
+    ),
+    (
+        "jenkins.json",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/dashboards/jenkins.json"
+        )),
+    ),
+    (
+        "postgres.json",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/dashboards/postgres.json"
+        )),
+    ),
+    (
+        "memcached.json",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/dashboards/memcached.json"
+        )),
+    ),
+];
+
+const HTTP_PORT: i64 = 3000;
+
+/// [Grafana](https://grafana.com/) showing the bundled dashboards and alerts. Users sign in using
+/// Gitea, where `admin` is granted the `Admin` role and all other users the `Viewer` role.
+#[derive(Construct)]
+#[construct(builder)]
+#[allow(dead_code)]
+pub struct Grafana {
+    #[construct(id)]
+    name: String,
+    #[construct(scope)]
+    scope: Rc<dyn Scope>,
+    #[construct(setter(into_value))]
+    namespace: Value<String>,
+    #[construct(setter(into))]
+    domain: String,
+    #[construct(setter(into))]
+    path: String,
+    #[construct(setter(into_value))]
+    volume_claim: Value<String>,
+    /// External URL of Gitea used to sign in users.
+    #[construct(setter(into))]
+    gitea_url: String,
+    /// In-cluster URL of Gitea used to request tokens and user information.
+    #[construct(setter(into))]
+    gitea_internal_url: String,
+    #[construct(setter(into_value))]
+    gitea_api_url: Value<String>,
+    /// Name of the secret storing the credentials of the Gitea root user. Used to register the
+    /// OAuth2 application of Grafana.
+    #[construct(setter(into_value))]
+    gitea_credentials: Value<String>,
+    /// Gitea user granted the `Admin` role.
+    #[construct(setter(into_value))]
+    admin: Value<String>,
+    /// Password of the local `admin` user.
+    #[construct(setter(into))]
+    admin_password: String,
+    #[construct(setter(into))]
+    prometheus_url: String,
+    #[construct(setter(into))]
+    alertmanager_url: Option<String>,
+}
+
+impl Grafana {
+    /// Returns the external URL of the web interface.
+    pub fn url(&self) -> String {
+        format!("https://{}{}/", self.domain, self.path)
+    }
+
+    pub fn ingress(&self) -> IngressServiceConfig {
+        IngressServiceConfig {
+            rewrite: false,
+            path: self.path.clone(),
+            service_name: self.name.clone(),
+            service_port: HTTP_PORT,
+            pod_labels: self.endpoint().pod_labels,
+            routed_paths: Vec::new(),
+            blocked_paths: Vec::new(),
+        }
+    }
+
+    /// Returns the web interface as endpoint of network policies.
+    pub fn endpoint(&self) -> NetworkEndpoint {
+        NetworkEndpoint::app(format!("grafana-{}", self.name), [HTTP_PORT])
+    }
+
+    /// Returns the Grafana pods as source of network policies.
+    pub fn peer(&self) -> NetworkPeer {
+        NetworkPeer::app(format!("grafana-{}", self.name))
+    }
+}
+
+impl GrafanaBuilder {
+    pub fn build(&mut self) -> Rc<Grafana> {
+        let this = Rc::new(Grafana {
+            name: self.name.clone(),
+            scope: self.scope.clone(),
+            namespace: self.namespace.clone().expect("missing field 'namespace'"),
+            domain: self.domain.clone().expect("missing field 'domain'"),
+            path: self.path.clone().unwrap_or("/grafana".into()),
+            volume_claim: self
+                .volume_claim
+                .clone()
+                .expect("missing field 'volume_claim'"),
+            gitea_url: self.gitea_url.clone().expect("missing field 'gitea_url'"),
+            gitea_internal_url: self
+                .gitea_internal_url
+                .clone()
+                .expect("missing field 'gitea_internal_url'"),
+            gitea_api_url: self
+                .gitea_api_url
+                .clone()
+                .expect("missing field 'gitea_api_url'"),
+            gitea_credentials: self
+                .gitea_credentials
+                .clone()
+                .expect("missing field 'gitea_credentials'"),
+            admin: self.admin.clone().expect("missing field 'admin'"),
+            admin_password: self
+                .admin_password
+                .clone()
+                .expect("missing field 'admin_password'"),
+            prometheus_url: self
+                .prometheus_url
+                .clone()
+                .expect("missing field 'prometheus_url'"),
+            alertmanager_url: self.alertmanager_url.clone().flatten(),
+        });
+
+        let name = &this.name;
+        let labels = crate::map! {
+            "app" = format!("grafana-{name}")
+        };
+
+        let mut datasources = vec![json::json!({
+            "name": "Prometheus",
+            "uid": "prometheus",
+            "type": "prometheus",
+            "access": "proxy",
+            "url": &this.prometheus_url,
+            "isDefault": true
+        })];
+        if let Some(url) = &this.alertmanager_url {
+            datasources.push(json::json!({
+                "name": "Alertmanager",
+                "uid": "alertmanager",
+                "type": "alertmanager",
+                "access": "proxy",
+                "url": url,
+                "jsonData": { "implementation": "prometheus" }
+            }));
+        }
+        let datasources = json::json!({ "apiVersion": 1, "datasources": datasources });
+        let providers = json::json!({
+            "apiVersion": 1,
+            "providers": [{
+                "name": "gitserver",
+                "folder": "gitserver",
+                "type": "file",
+                "options": { "path": "/var/lib/grafana-dashboards" }
+            }]
+        });
+        let provisioning = resource! {
+            &this, resource "kubernetes_config_map" "grafana-provisioning" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-provisioning")
+                }
+                data = crate::map! {
+                    "datasources.yaml" = crate::helper::yaml(&datasources),
+                    "dashboards.yaml" = crate::helper::yaml(&providers)
+                }
+            }
+        };
+        let dashboard_data: HashMap<_, _> = DASHBOARDS
+            .iter()
+            .map(|&(file, dashboard)| (file.to_string(), dashboard.into_value()))
+            .collect();
+        let dashboards = resource! {
+            &this, resource "kubernetes_config_map" "grafana-dashboards" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-dashboards")
+                }
+                data = dashboard_data
+            }
+        };
+        let credentials = resource! {
+            &this, resource "kubernetes_secret" "grafana-credentials" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-credentials")
+                }
+                data = crate::map! {
+                    "GF_SECURITY_ADMIN_PASSWORD" = &this.admin_password
+                }
+            }
+        };
+        let oauth_config = resource! {
+            &this, resource "kubernetes_secret" "grafana-oauth" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-oauth")
+                }
+                data = crate::map! {
+                    "oauth-app.py" = OAUTH_SCRIPT,
+                    "gitea_api.py" = GITEA_API_SCRIPT
+                }
+            }
+        };
+        let checksum = crate::helper::checksum((
+            OAUTH_SCRIPT,
+            GITEA_API_SCRIPT,
+            DASHBOARDS,
+            datasources.to_string(),
+            &this.admin_password,
+        ));
+
+        resource! {
+            &this, resource "kubernetes_service" "grafana" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+                spec {
+                    selector = &labels
+                    port {
+                        name = "http"
+                        port = HTTP_PORT
+                    }
+                }
+            }
+        };
+
+        let gitea_internal_url = this.gitea_internal_url.trim_end_matches('/');
+        resource! {
+            &this, resource "kubernetes_deployment" "grafana" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+                spec {
+                    replicas = "1"
+                    selector {
+                        match_labels = &labels
+                    }
+                    template {
+                        metadata {
+                            labels = &labels
+                            annotations = crate::map! {
+                                "gitserver/checksum" = &checksum
+                            }
+                        }
+                        spec {
+                            security_context = security::deployment::pod(472, 472)
+                            init_container {
+                                name = "oauth-app"
+                                image = "python:3.11-alpine"
+                                command = ["python3", "/config/oauth-app.py"]
+                                env {
+                                    name = "GITEA_API_URL"
+                                    value = &this.gitea_api_url
+                                }
+                                env {
+                                    name = "APP_NAME"
+                                    value = name
+                                }
+                                env {
+                                    name = "REDIRECT_URI"
+                                    value = format!("{}login/generic_oauth", this.url())
+                                }
+                                env {
+                                    name = "OUTPUT_DIR"
+                                    value = "/oauth"
+                                }
+                                env_from {
+                                    secret_ref {
+                                        name = &this.gitea_credentials
+                                    }
+                                }
+                                volume_mount {
+                                    name = "config"
+                                    mount_path = "/config"
+                                }
+                                volume_mount {
+                                    name = "oauth"
+                                    mount_path = "/oauth"
+                                }
+                                security_context = security::deployment::init_container()
+                            }
+                            container {
+                                name = "grafana"
+                                image = "grafana/grafana:10.2.2"
+                                port {
+                                    name = "http"
+                                    container_port = HTTP_PORT
+                                }
+                                env {
+                                    name = "GF_SERVER_ROOT_URL"
+                                    value = this.url()
+                                }
+                                env {
+                                    name = "GF_SERVER_SERVE_FROM_SUB_PATH"
+                                    value = "true"
+                                }
+                                env {
+                                    name = "GF_ANALYTICS_REPORTING_ENABLED"
+                                    value = "false"
+                                }
+                                env {
+                                    name = "GF_AUTH_GENERIC_OAUTH_ENABLED"
+                                    value = "true"
+                                }
+                                env {
+                                    name = "GF_AUTH_GENERIC_OAUTH_NAME"
+                                    value = "Gitea"
+                                }
+                                env {
+                                    name = "GF_AUTH_GENERIC_OAUTH_ALLOW_SIGN_UP"
+                                    value = "true"
+                                }
+                                env {
+                                    name = "GF_AUTH_GENERIC_OAUTH_CLIENT_ID__FILE"
+                                    value = "/oauth/client_id"
+                                }
+                                env {
+                                    name = "GF_AUTH_GENERIC_OAUTH_CLIENT_SECRET__FILE"
+                                    value = "/oauth/client_secret"
+                                }
+                                env {
+                                    name = "GF_AUTH_GENERIC_OAUTH_SCOPES"
+                                    value = "openid email profile"
+                                }
+                                env {
+                                    name = "GF_AUTH_GENERIC_OAUTH_AUTH_URL"
+                                    value = format!("{}login/oauth/authorize", this.gitea_url)
+                                }
+                                env {
+                                    name = "GF_AUTH_GENERIC_OAUTH_TOKEN_URL"
+                                    value = format!("{gitea_internal_url}/login/oauth/access_token")
+                                }
+                                env {
+                                    name = "GF_AUTH_GENERIC_OAUTH_API_URL"
+                                    value = format!("{gitea_internal_url}/login/oauth/userinfo")
+                                }
+                                env {
+                                    name = "GF_AUTH_GENERIC_OAUTH_LOGIN_ATTRIBUTE_PATH"
+                                    value = "preferred_username"
+                                }
+                                env {
+                                    name = "GF_AUTH_GENERIC_OAUTH_ROLE_ATTRIBUTE_PATH"
+                                    value = format!(
+                                        "preferred_username == '{}' && 'Admin' || 'Viewer'",
+                                        this.admin.get()
+                                    )
+                                }
+                                env_from {
+                                    secret_ref {
+                                        name = &credentials.metadata[0].name
+                                    }
+                                }
+                                volume_mount {
+                                    name = "oauth"
+                                    mount_path = "/oauth"
+                                }
+                                volume_mount {
+                                    name = "provisioning"
+                                    mount_path = "/etc/grafana/provisioning/datasources/datasources.yaml"
+                                    sub_path = "datasources.yaml"
+                                }
+                                volume_mount {
+                                    name = "provisioning"
+                                    mount_path = "/etc/grafana/provisioning/dashboards/dashboards.yaml"
+                                    sub_path = "dashboards.yaml"
+                                }
+                                volume_mount {
+                                    name = "dashboards"
+                                    mount_path = "/var/lib/grafana-dashboards"
+                                }
+                                volume_mount {
+                                    name = "data"
+                                    mount_path = "/var/lib/grafana"
+                                }
+                                volume_mount {
+                                    name = "logs"
+                                    mount_path = "/var/log/grafana"
+                                }
+                                volume_mount {
+                                    name = "tmp"
+                                    mount_path = "/tmp"
+                                }
+                                readiness_probe {
+                                    http_get {
+                                        path = format!("{}/api/health", this.path)
+                                        port = "http"
+                                    }
+                                }
+                                liveness_probe {
+                                    tcp_socket {
+                                        port = "http"
+                                    }
+                                    success_threshold = 1
+                                    failure_threshold = 10
+                                    period_seconds = 12
+                                    timeout_seconds = 5
+                                }
+                                security_context = security::deployment::container()
+                            }
+                            volume {
+                                name = "config"
+                                secret {
+                                    secret_name = &oauth_config.metadata[0].name
+                                }
+                            }
+                            volume {
+                                name = "oauth"
+                                empty_dir {}
+                            }
+                            volume {
+                                name = "provisioning"
+                                config_map {
+                                    name = &provisioning.metadata[0].name
+                                }
+                            }
+                            volume {
+                                name = "dashboards"
+                                config_map {
+                                    name = &dashboards.metadata[0].name
+                                }
+                            }
+                            volume {
+                                name = "data"
+                                persistent_volume_claim {
+                                    claim_name = &this.volume_claim
+                                }
+                            }
+                            volume {
+                                name = "logs"
+                                empty_dir {}
+                            }
+                            volume {
+                                name = "tmp"
+                                empty_dir {}
+                            }
+                        }
+                    }
+                }
+            }
+        };
+
+        this
+    }
+}
@@ -0,0 +1,10 @@
+use tf_bindgen::Value;
+
+/// Backend used by Gitea to index code and issues.
+#[derive(Clone)]
+pub enum Indexer {
+    /// Embedded bleve index stored on the given volume claim.
+    Bleve(Value<String>),
+    /// Elasticsearch 7 cluster reachable using the given URL (including credentials).
+    Elasticsearch(String),
+}
@@ -1,15 +1,54 @@
+use std::collections::HashMap;
 use std::rc::Rc;
 
 use tf_bindgen::codegen::{resource, Construct};
+use tf_bindgen::value::IntoValue;
 use tf_bindgen::{Scope, Value};
 use tf_kubernetes::kubernetes::resource::kubernetes_ingress_v1::{self, *};
 
+use super::network_policy::{NetworkEndpoint, NetworkPeer, NetworkPolicy};
+
 #[derive(Clone)]
 pub struct IngressServiceConfig {
     pub rewrite: bool,
     pub path: String,
     pub service_name: String,
     pub service_port: i64,
+    /// Labels of the pods behind the service. Their container port must equal `service_port`.
+    pub pod_labels: HashMap<String, String>,
+    /// Paths below `path` routed to the service (e.g. `/api/packages`). All paths are routed if
+    /// empty.
+    pub routed_paths: Vec<String>,
+    /// Paths below `path` denied by the ingress controller (e.g. `/prometheus`).
+    pub blocked_paths: Vec<String>,
+}
+
+impl IngressServiceConfig {
+    /// Returns the ingress path routing `subpath` below `path` to the service.
+    fn http_path(&self, subpath: &str) -> KubernetesIngressV1SpecRuleHttpPath {
+        let port = KubernetesIngressV1SpecRuleHttpPathBackendServicePort::builder()
+            .number(self.service_port)
+            .build();
+        let service = KubernetesIngressV1SpecRuleHttpPathBackendService::builder()
+            .name(&self.service_name)
+            .port(port)
+            .build();
+        let backend = KubernetesIngressV1SpecRuleHttpPathBackend::builder()
+            .service(service)
+            .build();
+        let path = match self.rewrite {
+            true => match subpath {
+                "" => format!("{}/(.*)", self.path),
+                subpath => format!("{}/({}/.*)", self.path, &subpath[1..]),
+            },
+            false => format!("/({}{subpath}/.*)", &self.path[1..]),
+        };
+        KubernetesIngressV1SpecRuleHttpPath::builder()
+            .path_type("Prefix")
+            .path(&path)
+            .backend(backend)
+            .build()
+    }
 }
 
 #[derive(Construct)]
@@ -24,6 +63,12 @@ pub struct Ingress {
     namespace: Value<String>,
     #[construct(setter(into))]
     services: Vec<IngressServiceConfig>,
+    /// Additional annotations passed to the ingress controller.
+    #[construct(setter(into))]
+    annotations: HashMap<String, String>,
+    /// Pods of the ingress controller. Allowed to connect to the services if set.
+    #[construct(setter(into))]
+    controller: Option<NetworkPeer>,
 }
 
 impl IngressBuilder {
@@ -33,41 +78,63 @@ impl IngressBuilder {
             scope: self.scope.clone(),
             namespace: self.namespace.clone().expect("missing field namespace"),
             services: self.services.clone().expect("missing field services"),
+            annotations: self.annotations.clone().unwrap_or_default(),
+            controller: self.controller.clone().flatten(),
         });
         let name = &self.name;
 
+        if let Some(controller) = &this.controller {
+            // Services may be exposed at multiple paths (e.g. the registry of Gitea).
+            let mut backends: HashMap<&str, NetworkEndpoint> = HashMap::new();
+            for config in &this.services {
+                let endpoint = backends.entry(config.service_name.as_str()).or_default();
+                endpoint.pod_labels = config.pod_labels.clone();
+                if !endpoint.ports.contains(&config.service_port) {
+                    endpoint.ports.push(config.service_port);
+                }
+            }
+            for (service, endpoint) in backends {
+                NetworkPolicy::create(&this, format!("{name}-{service}"))
+                    .namespace(&this.namespace)
+                    .endpoint(endpoint)
+                    .from(vec![controller.clone()])
+                    .build();
+            }
+        }
+
         let paths: Vec<_> = this
             .services
             .iter()
-            .map(|config| {
-                let port = KubernetesIngressV1SpecRuleHttpPathBackendServicePort::builder()
-                    .number(config.service_port)
-                    .build();
-                let service = KubernetesIngressV1SpecRuleHttpPathBackendService::builder()
-                    .name(&config.service_name)
-                    .port(port)
-                    .build();
-                let backend = KubernetesIngressV1SpecRuleHttpPathBackend::builder()
-                    .service(service)
-                    .build();
-                let path = match config.rewrite {
-                    true => format!("{}/(.*)", config.path),
-                    false => format!("/({}/.*)", &config.path[1..]),
-                };
-                KubernetesIngressV1SpecRuleHttpPath::builder()
-                    .path_type("Prefix")
-                    .path(&path)
-                    .backend(backend)
-                    .build()
+            .flat_map(|config| match config.routed_paths.is_empty() {
+                true => vec![config.http_path("")],
+                false => config
+                    .routed_paths
+                    .iter()
+                    .map(|routed| config.http_path(routed))
+                    .collect(),
             })
             .collect();
+        let blocked_paths: Vec<_> = this
+            .services
+            .iter()
+            .flat_map(|config| {
+                config
+                    .blocked_paths
+                    .iter()
+                    .map(|blocked| config.http_path(blocked))
+            })
+            .collect();
+        let mut annotations = crate::map! {
+            "nginx.ingress.kubernetes.io/use-regex" = "true",
+            "nginx.ingress.kubernetes.io/rewrite-target" = "/$1"
+        };
+        for (key, value) in &this.annotations {
+            annotations.insert(key.clone(), value.into_value());
+        }
         resource! {
             &this, resource "kubernetes_ingress_v1" "ingress" {
                 metadata {
-                    annotations = crate::map! {
-                        "nginx.ingress.kubernetes.io/use-regex" = "true",
-                        "nginx.ingress.kubernetes.io/rewrite-target" = "/$1"
-                    }
+                    annotations = annotations
                     namespace = &this.namespace
                     name = format!("{name}-ingress")
                 }
@@ -80,6 +147,29 @@ impl IngressBuilder {
                 }
             }
         };
+
+        // Longer paths take precedence, so requests of blocked paths are denied by this ingress.
+        if !blocked_paths.is_empty() {
+            resource! {
+                &this, resource "kubernetes_ingress_v1" "blocked" {
+                    metadata {
+                        annotations = crate::map! {
+                            "nginx.ingress.kubernetes.io/use-regex" = "true",
+                            "nginx.ingress.kubernetes.io/denylist-source-range" = "0.0.0.0/0,::/0"
+                        }
+                        namespace = &this.namespace
+                        name = format!("{name}-blocked")
+                    }
+                    spec {
+                        rule {
+                            http {
+                                path = blocked_paths
+                            }
+                        }
+                    }
+                }
+            };
+        }
         this
     }
 }
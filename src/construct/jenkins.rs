@@ -1,6 +1,7 @@
 use std::rc::Rc;
 
 use tf_bindgen::codegen::{resource, Construct};
+use tf_bindgen::json;
 use tf_bindgen::value::Value;
 use tf_bindgen::Scope;
 use tf_kubernetes::kubernetes::resource::{
@@ -8,7 +9,12 @@ use tf_kubernetes::kubernetes::resource::{
     kubernetes_deployment, kubernetes_secret, kubernetes_service, kubernetes_service_account,
 };
 
+use super::ci::CiServer;
 use super::ingress::IngressServiceConfig;
+use super::mail::{MailConfig, MailTls};
+use super::monitoring::{Monitoring, PodMonitor};
+use super::network_policy::{NetworkEndpoint, NetworkPeer};
+use super::security;
 
 #[derive(Construct)]
 #[construct(builder)]
@@ -23,17 +29,151 @@ pub struct Jenkins {
     path: String,
     #[construct(setter(into))]
     domain: String,
+    /// SMTP server used to send build notifications. Mails are disabled if not set.
+    #[construct(setter(into))]
+    mail: Option<MailConfig>,
+    /// OpenID Connect provider used to authenticate users.
+    #[construct(setter(into))]
+    sso: Option<JenkinsSso>,
+    /// Exposes metrics at `<path>/prometheus/` using the Prometheus plugin. The endpoint is not
+    /// exposed by the ingress.
+    #[construct(setter(into))]
+    monitoring: Option<Monitoring>,
 }
 
-impl Jenkins {
-    pub fn ingress(&self) -> IngressServiceConfig {
+/// Single sign-on configuration of Jenkins. Group membership is mapped onto Jenkins roles.
+#[derive(Clone)]
+pub struct JenkinsSso {
+    pub discovery_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// Groups granted the `admin` role.
+    pub admin_groups: Vec<String>,
+    /// Groups granted the `developer` role (read, build and cancel jobs).
+    pub developer_groups: Vec<String>,
+}
+
+impl CiServer for Jenkins {
+    fn kind(&self) -> &'static str {
+        "Jenkins"
+    }
+
+    fn url(&self) -> String {
+        format!("https://{}{}", self.domain, self.path)
+    }
+
+    fn ingress(&self) -> IngressServiceConfig {
         IngressServiceConfig {
             rewrite: false,
             path: self.path.clone(),
             service_name: format!("{}-service", self.name),
             service_port: 8080,
+            pod_labels: self.endpoint().pod_labels,
+            routed_paths: Vec::new(),
+            // Metrics are only scraped inside the cluster.
+            blocked_paths: match self.monitoring {
+                Some(_) => vec!["/prometheus".to_string()],
+                None => Vec::new(),
+            },
         }
     }
+
+    fn endpoint(&self) -> NetworkEndpoint {
+        NetworkEndpoint::app(&self.name, [8080, 50000])
+    }
+
+    fn peer(&self) -> NetworkPeer {
+        NetworkPeer::app(&self.name)
+    }
+}
+
+/// Returns the CasC configuration of the `mailer` and `email-ext` plugins. The password will be
+/// read from the environment variable `SMTP_PASSWORD`.
+fn mailer_casc(mail: &MailConfig) -> json::Map<String, json::Value> {
+    let use_ssl = mail.tls == MailTls::Tls;
+    let use_tls = mail.tls == MailTls::StartTls;
+    let mut mailer = json::json!({
+        "smtpHost": &mail.host,
+        "smtpPort": mail.port.to_string(),
+        "useSsl": use_ssl,
+        "useTls": use_tls,
+        "replyToAddress": &mail.from
+    });
+    let mut account = json::json!({
+        "address": &mail.from,
+        "smtpHost": &mail.host,
+        "smtpPort": mail.port.to_string(),
+        "useSsl": use_ssl,
+        "useTls": use_tls
+    });
+    if let Some(user) = &mail.user {
+        mailer["authentication"] = json::json!({
+            "username": user,
+            "password": "${SMTP_PASSWORD}"
+        });
+        account["smtpUsername"] = user.as_str().into();
+        account["smtpPassword"] = "${SMTP_PASSWORD}".into();
+    }
+    let mut casc = json::Map::new();
+    casc.insert("mailer".to_string(), mailer);
+    casc.insert(
+        "email-ext".to_string(),
+        json::json!({ "mailAccount": account }),
+    );
+    casc
+}
+
+/// Returns the CasC configuration of the OpenID Connect security realm and the role based
+/// authorization strategy. The client secret will be read from the environment variable
+/// `OIDC_CLIENT_SECRET`.
+fn sso_casc(sso: &JenkinsSso) -> json::Value {
+    let entries = |groups: &[String]| -> Vec<json::Value> {
+        groups
+            .iter()
+            .map(|group| json::json!({ "group": group }))
+            .collect()
+    };
+    json::json!({
+        "securityRealm": {
+            "oic": {
+                "clientId": &sso.client_id,
+                "clientSecret": "${OIDC_CLIENT_SECRET}",
+                "wellKnownOpenIDConfigurationUrl": &sso.discovery_url,
+                "automanualconfigure": "auto",
+                "scopes": "openid email profile groups",
+                "userNameField": "email",
+                "fullNameFieldName": "name",
+                "emailFieldName": "email",
+                "groupsFieldName": "groups"
+            }
+        },
+        "authorizationStrategy": {
+            "roleBased": {
+                "roles": {
+                    "global": [
+                        {
+                            "name": "admin",
+                            "permissions": ["Overall/Administer"],
+                            "entries": entries(&sso.admin_groups)
+                        },
+                        {
+                            "name": "developer",
+                            "permissions": [
+                                "Overall/Read",
+                                "View/Read",
+                                "Job/Read",
+                                "Job/Build",
+                                "Job/Cancel",
+                                "Job/Workspace",
+                                "Run/Replay"
+                            ],
+                            "entries": entries(&sso.developer_groups)
+                        }
+                    ]
+                }
+            }
+        }
+    })
 }
 
 impl JenkinsBuilder {
@@ -44,6 +184,9 @@ impl JenkinsBuilder {
             path: self.path.clone().expect("missing field 'path'"),
             domain: self.domain.clone().expect("missing field 'domain'"),
             namespace: self.namespace.clone().expect("missing field 'namespace'"),
+            mail: self.mail.clone().flatten(),
+            sso: self.sso.clone().flatten(),
+            monitoring: self.monitoring.clone().flatten(),
         });
 
         let name = &this.name;
@@ -93,6 +236,50 @@ impl JenkinsBuilder {
             }
         };
 
+        let mut location = json::json!({ "url": format!("https://{domain}{path}") });
+        let mut unclassified = json::Map::new();
+        if let Some(mail) = &this.mail {
+            // Jenkins sends mails from the admin address, which must be accepted by the SMTP
+            // server.
+            location["adminAddress"] = mail.from.as_str().into();
+            unclassified.extend(mailer_casc(mail));
+        }
+        unclassified.insert("location".to_string(), location);
+        let smtp_password = this
+            .mail
+            .as_ref()
+            .and_then(|mail| mail.password.clone())
+            .unwrap_or_default();
+        let oidc_client_secret = this
+            .sso
+            .as_ref()
+            .map(|sso| sso.client_secret.clone())
+            .unwrap_or_default();
+        let secret_env = resource! {
+            &this, resource "kubernetes_secret" "jenkins-env" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-env")
+                }
+                data = crate::map! {
+                    "SMTP_PASSWORD" = &smtp_password,
+                    "OIDC_CLIENT_SECRET" = &oidc_client_secret
+                }
+            }
+        };
+
+        if this.monitoring.is_some() {
+            unclassified.insert(
+                "prometheusConfiguration".to_string(),
+                json::json!({ "path": "prometheus", "useAuthenticatedEndpoint": false }),
+            );
+        }
+        let mut casc_config = json::json!({ "unclassified": unclassified });
+        if let Some(sso) = &this.sso {
+            casc_config["jenkins"] = sso_casc(sso);
+        }
+        let casc_config = crate::helper::yaml(&casc_config);
+        let checksum = crate::helper::checksum((&casc_config, &smtp_password, &oidc_client_secret));
         let casc = resource! {
             &this, resource "kubernetes_secret" "jenkins-casc" {
                 metadata {
@@ -100,11 +287,7 @@ impl JenkinsBuilder {
                     name = format!("{name}-casc")
                 }
                 data = crate::map! {
-                    "casc.yaml" = format!(r#"
-unclassified:
-  location:
-    url: https://{domain}{path}
-"#),
+                    "casc.yaml" = casc_config,
                     "install-plugins.sh" = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/script/jenkins/install-plugins.sh"))
                 }
             }
@@ -138,14 +321,12 @@ unclassified:
                     template {
                         metadata {
                             labels = &labels
+                            annotations = crate::map! {
+                                "gitserver/checksum" = checksum
+                            }
                         }
                         spec {
-                            security_context {
-                                fs_group = "1000"
-                                run_as_user = "1000"
-                                run_as_group = "100"
-                                run_as_non_root = true
-                            }
+                            security_context = security::deployment::pod(1000, 1000)
                             service_account_name = &service_account.metadata[0].name
                             init_container {
                                 name = "install-plugins"
@@ -159,11 +340,16 @@ unclassified:
                                     name = "jenkins-casc"
                                     mount_path = "/config"
                                 }
+                                volume_mount {
+                                    name = "tmp"
+                                    mount_path = "/tmp"
+                                }
                                 env_from {
                                     config_map_ref {
                                         name = &config.metadata[0].name
                                     }
                                 }
+                                security_context = security::deployment::init_container()
                             }
                             container {
                                 name = "jenkins"
@@ -207,11 +393,25 @@ unclassified:
                                         name = &config.metadata[0].name
                                     }
                                 }
+                                volume_mount {
+                                    name = "tmp"
+                                    mount_path = "/tmp"
+                                }
+                                env_from {
+                                    secret_ref {
+                                        name = &secret_env.metadata[0].name
+                                    }
+                                }
+                                security_context = security::deployment::container()
                             }
                             volume {
                                 name = "jenkins-data"
                                 empty_dir{}
                             }
+                            volume {
+                                name = "tmp"
+                                empty_dir {}
+                            }
                             volume {
                                 name = "jenkins-casc"
                                 secret {
@@ -224,6 +424,17 @@ unclassified:
             }
         };
 
+        if let Some(monitoring) = &this.monitoring {
+            PodMonitor::create(&this, format!("{name}-metrics"))
+                .namespace(&this.namespace)
+                .monitoring(monitoring.clone())
+                .pod_labels([("app".to_string(), name.clone())])
+                .port("http")
+                .path(format!("{path}/prometheus/"))
+                .metrics_port(8080)
+                .build();
+        }
+
         resource! {
             &this, resource "kubernetes_service" "jenkins" {
                 metadata {
@@ -4,10 +4,19 @@ use std::rc::Rc;
 use tf_bindgen::codegen::{resource, Construct};
 use tf_bindgen::value::{IntoValue, Value};
 use tf_bindgen::Scope;
+use tf_kubernetes::kubernetes::resource::kubernetes_job::{
+    self, KubernetesJobSpecTemplateSpecContainerSecurityContext,
+    KubernetesJobSpecTemplateSpecContainerSecurityContextCapabilities,
+};
 use tf_kubernetes::kubernetes::resource::kubernetes_persistent_volume;
 
 use super::local_dir_volume_claim::{LocalDirVolumeClaim, LocalDirVolumeClaimBuilder};
+use super::security;
 
+/// Host directory `mount_path` of `node` used as persistent volume. Host paths are not affected by
+/// the `fsGroup` of pods, so a job creates the directory and transfers it to `owner` (user and
+/// group of the consuming pods) before the volume is created. The job requires a namespace
+/// allowing privileged pods.
 #[derive(Construct)]
 #[construct(builder)]
 #[allow(dead_code)]
@@ -16,6 +25,9 @@ pub struct LocalDirVolume {
     name: String,
     #[construct(scope)]
     scope: Rc<dyn Scope>,
+    /// Namespace of the job preparing the directory.
+    #[construct(setter(into_value))]
+    namespace: Value<String>,
     #[construct(setter(into_value))]
     storage: Value<String>,
     #[construct(setter(into_value))]
@@ -24,6 +36,8 @@ pub struct LocalDirVolume {
     mount_path: Value<String>,
     #[construct(setter(into_value))]
     node: Value<String>,
+    #[construct(setter(into))]
+    owner: (i64, i64),
     #[construct(skip)]
     volume_ref: RefCell<Option<Value<String>>>,
 }
@@ -46,6 +60,7 @@ impl LocalDirVolumeBuilder {
         let this = Rc::new(LocalDirVolume {
             name: self.name.clone(),
             scope: self.scope.clone(),
+            namespace: self.namespace.clone().expect("missing field 'namespace'"),
             storage: self.storage.clone().expect("missing field 'council'"),
             storage_class: self
                 .storage_class
@@ -53,14 +68,77 @@ impl LocalDirVolumeBuilder {
                 .expect("missing field 'storage_class'"),
             mount_path: self.mount_path.clone().expect("missing field 'mount_path'"),
             node: self.node.clone().expect("missing field 'node'"),
+            owner: self.owner.expect("missing field 'owner'"),
             volume_ref: RefCell::new(None),
         });
         let name = &this.name;
+        let (user, group) = this.owner;
+
+        // Only runs again if the owner changed. Also transfers directories created by previous
+        // versions running as root.
+        let checksum = crate::helper::checksum((this.mount_path.get().as_str(), user, group));
+        let capabilities =
+            KubernetesJobSpecTemplateSpecContainerSecurityContextCapabilities::builder()
+                .drop(["ALL"])
+                .add(["CHOWN", "DAC_READ_SEARCH"])
+                .build();
+        let owner_security_context =
+            KubernetesJobSpecTemplateSpecContainerSecurityContext::builder()
+                .allow_privilege_escalation(false)
+                .read_only_root_filesystem(true)
+                .run_as_user("0")
+                .run_as_non_root(false)
+                .capabilities(capabilities)
+                .build();
+        let owner_job = resource! {
+            &this, resource "kubernetes_job" "owner" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-owner")
+                }
+                spec {
+                    backoff_limit = 6
+                    template {
+                        metadata {
+                            annotations = crate::map! {
+                                "gitserver/checksum" = checksum
+                            }
+                        }
+                        spec {
+                            restart_policy = "OnFailure"
+                            node_name = &this.node
+                            security_context = security::job::root_pod()
+                            container {
+                                name = "chown"
+                                image = "busybox:1.36"
+                                command = ["chown", "-R", format!("{user}:{group}"), "/data"]
+                                volume_mount {
+                                    name = "data"
+                                    mount_path = "/data"
+                                }
+                                security_context = owner_security_context
+                            }
+                            volume {
+                                name = "data"
+                                host_path {
+                                    path = &this.mount_path
+                                    r#type = "DirectoryOrCreate"
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        };
 
         let volume = resource! {
             &this, resource "kubernetes_persistent_volume" "pv-local" {
                 metadata {
                     name = format!("{name}-local-pv")
+                    // Creates the volume once the directory was prepared.
+                    annotations = crate::map! {
+                        "gitserver/owner-job" = &owner_job.metadata[0].name
+                    }
                 }
                 spec {
                     volume_mode = "Filesystem"
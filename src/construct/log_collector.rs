@@ -0,0 +1,414 @@
+use std::rc::Rc;
+
+use tf_bindgen::codegen::{resource, Construct};
+use tf_bindgen::{Scope, Value};
+use tf_kubernetes::kubernetes::resource::kubernetes_daemonset::{
+    self, KubernetesDaemonsetSpecTemplateSpecContainer,
+    KubernetesDaemonsetSpecTemplateSpecContainerEnv,
+    KubernetesDaemonsetSpecTemplateSpecContainerVolumeMount,
+    KubernetesDaemonsetSpecTemplateSpecVolume, KubernetesDaemonsetSpecTemplateSpecVolumeHostPath,
+};
+use tf_kubernetes::kubernetes::resource::{
+    kubernetes_config_map, kubernetes_role, kubernetes_role_binding, kubernetes_service_account,
+};
+
+use url::Url;
+
+use super::monitoring::{Monitoring, PodMonitor};
+use super::security;
+
+const HTTP_PORT: i64 = 2020;
+
+const ROTATE_SCRIPT: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/script/fluent-bit/rotate.sh"
+));
+
+/// Destination of the collected logs.
+#[derive(Clone)]
+pub enum LogSink {
+    /// Writes one file per container into `path` of each node. Files larger than `max_size` MiB
+    /// are rotated, keeping one previous file, and files not written for `retention_days` are
+    /// deleted.
+    File {
+        path: String,
+        max_size: i64,
+        retention_days: i64,
+    },
+    /// Pushes logs to the [Loki](https://grafana.com/oss/loki/) server at this URL.
+    Loki(Url),
+    /// Sends logs to a syslog server using RFC 5424.
+    Syslog { host: String, port: i64, tcp: bool },
+}
+
+/// [Fluent Bit](https://fluentbit.io/) daemon set collecting the logs of all containers (including
+/// init containers) of `source_namespace`. Every record is labeled with the `app` label of its pod as
+/// `component`, the pod and the container name.
+#[derive(Construct)]
+#[construct(builder)]
+#[allow(dead_code)]
+pub struct LogCollector {
+    #[construct(id)]
+    name: String,
+    #[construct(scope)]
+    scope: Rc<dyn Scope>,
+    #[construct(setter(into_value))]
+    namespace: Value<String>,
+    /// Namespace of the collected containers. Defaults to `namespace`.
+    #[construct(setter(into_value))]
+    source_namespace: Value<String>,
+    #[construct(setter(into))]
+    sink: LogSink,
+    /// Exposes the metrics of Fluent Bit scraped by Prometheus.
+    #[construct(setter(into))]
+    monitoring: Option<Monitoring>,
+}
+
+impl LogCollectorBuilder {
+    pub fn build(&mut self) -> Rc<LogCollector> {
+        let this = Rc::new(LogCollector {
+            name: self.name.clone(),
+            scope: self.scope.clone(),
+            namespace: self.namespace.clone().expect("missing field 'namespace'"),
+            source_namespace: self
+                .source_namespace
+                .clone()
+                .or_else(|| self.namespace.clone())
+                .expect("missing field 'namespace'"),
+            sink: self.sink.clone().expect("missing field 'sink'"),
+            monitoring: self.monitoring.clone().flatten(),
+        });
+
+        let name = &this.name;
+        let labels = crate::map! {
+            "app" = format!("fluent-bit-{name}")
+        };
+
+        if let Some(monitoring) = &this.monitoring {
+            PodMonitor::create(&this, format!("{name}-metrics"))
+                .namespace(&this.namespace)
+                .monitoring(monitoring.clone())
+                .pod_labels([("app".to_string(), format!("fluent-bit-{name}"))])
+                .port("http")
+                .path("/api/v1/metrics/prometheus")
+                .metrics_port(HTTP_PORT)
+                .build();
+        }
+
+        // The kubernetes filter reads the labels of the pods.
+        let role = resource! {
+            &this, resource "kubernetes_role" "fluent-bit" {
+                metadata {
+                    namespace = &this.source_namespace
+                    name = name
+                }
+                rule {
+                    api_groups = [""]
+                    resources = ["pods"]
+                    verbs = ["get", "list", "watch"]
+                }
+            }
+        };
+        let service_account = resource! {
+            &this, resource "kubernetes_service_account" "fluent-bit" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+            }
+        };
+        resource! {
+            &this, resource "kubernetes_role_binding" "fluent-bit" {
+                metadata {
+                    namespace = &this.source_namespace
+                    name = name
+                }
+                role_ref {
+                    api_group = "rbac.authorization.k8s.io"
+                    kind = "Role"
+                    name = &role.metadata[0].name
+                }
+                subject {
+                    kind = "ServiceAccount"
+                    name = &service_account.metadata[0].name
+                    namespace = &this.namespace
+                }
+            }
+        };
+
+        let config = fluent_bit_config(&this.source_namespace.get(), &this.sink);
+        let checksum = crate::helper::checksum((&config, ROTATE_SCRIPT));
+        let config = resource! {
+            &this, resource "kubernetes_config_map" "fluent-bit" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+                data = crate::map! {
+                    "fluent-bit.conf" = config,
+                    "rotate.sh" = ROTATE_SCRIPT
+                }
+            }
+        };
+        let mut output_mounts = Vec::new();
+        let mut output_volumes = Vec::new();
+        let mut output_containers = Vec::new();
+        if let LogSink::File {
+            path,
+            max_size,
+            retention_days,
+        } = &this.sink
+        {
+            let host_path = KubernetesDaemonsetSpecTemplateSpecVolumeHostPath::builder()
+                .path(path)
+                .r#type("DirectoryOrCreate")
+                .build();
+            output_volumes.push(
+                KubernetesDaemonsetSpecTemplateSpecVolume::builder()
+                    .name("output")
+                    .host_path(host_path)
+                    .build(),
+            );
+            let output_mount = || {
+                KubernetesDaemonsetSpecTemplateSpecContainerVolumeMount::builder()
+                    .name("output")
+                    .mount_path("/logs")
+                    .build()
+            };
+            output_mounts.push(output_mount());
+            let env = [
+                ("MAX_SIZE", (max_size * 1024 * 1024).to_string()),
+                ("RETENTION_DAYS", retention_days.to_string()),
+            ];
+            let config_mount = KubernetesDaemonsetSpecTemplateSpecContainerVolumeMount::builder()
+                .name("config")
+                .mount_path("/fluent-bit/config")
+                .build();
+            output_containers.push(
+                KubernetesDaemonsetSpecTemplateSpecContainer::builder()
+                    .name("rotate")
+                    .image("busybox:1.36")
+                    .command(["sh", "/fluent-bit/config/rotate.sh"])
+                    .env(
+                        env.map(|(name, value)| {
+                            KubernetesDaemonsetSpecTemplateSpecContainerEnv::builder()
+                                .name(name)
+                                .value(value)
+                                .build()
+                        })
+                        .to_vec(),
+                    )
+                    .volume_mount(vec![config_mount, output_mount()])
+                    .security_context(security::daemonset::container())
+                    .build(),
+            );
+        }
+
+        resource! {
+            &this, resource "kubernetes_daemonset" "fluent-bit" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+                spec {
+                    selector {
+                        match_labels = &labels
+                    }
+                    template {
+                        metadata {
+                            labels = &labels
+                            annotations = crate::map! {
+                                "gitserver/checksum" = checksum
+                            }
+                        }
+                        spec {
+                            service_account_name = &service_account.metadata[0].name
+                            // Container logs of the node are only readable by root.
+                            security_context = security::daemonset::root_pod()
+                            container {
+                                name = "fluent-bit"
+                                image = "cr.fluentbit.io/fluent/fluent-bit:2.2.0"
+                                args = ["--config=/fluent-bit/config/fluent-bit.conf"]
+                                port {
+                                    name = "http"
+                                    container_port = HTTP_PORT
+                                }
+                                volume_mount {
+                                    name = "config"
+                                    mount_path = "/fluent-bit/config"
+                                }
+                                volume_mount {
+                                    name = "varlog"
+                                    mount_path = "/var/log"
+                                    read_only = true
+                                }
+                                volume_mount {
+                                    name = "state"
+                                    mount_path = "/var/fluent-bit/state"
+                                }
+                                volume_mount = output_mounts
+                                readiness_probe {
+                                    http_get {
+                                        path = "/api/v1/health"
+                                        port = "http"
+                                    }
+                                }
+                                liveness_probe {
+                                    http_get {
+                                        path = "/"
+                                        port = "http"
+                                    }
+                                    success_threshold = 1
+                                    failure_threshold = 10
+                                    period_seconds = 12
+                                    timeout_seconds = 5
+                                }
+                                security_context = security::daemonset::container()
+                            }
+                            container = output_containers
+                            volume {
+                                name = "config"
+                                config_map {
+                                    name = &config.metadata[0].name
+                                }
+                            }
+                            volume {
+                                name = "varlog"
+                                host_path {
+                                    path = "/var/log"
+                                }
+                            }
+                            // Stores the read offsets, so logs are not shipped twice on restart.
+                            volume {
+                                name = "state"
+                                host_path {
+                                    path = format!("/var/lib/fluent-bit/{}", this.namespace.get())
+                                    r#type = "DirectoryOrCreate"
+                                }
+                            }
+                            volume = output_volumes
+                        }
+                    }
+                }
+            }
+        };
+
+        this
+    }
+}
+
+/// Returns the Fluent Bit configuration reading the container logs of `namespace` and sending
+/// them to `sink`.
+fn fluent_bit_config(namespace: &str, sink: &LogSink) -> String {
+    let output = match sink {
+        LogSink::File { .. } => "[OUTPUT]
+    Name file
+    Match kube.*
+    Path /logs
+    Format plain
+"
+        .to_string(),
+        LogSink::Loki(url) => {
+            let host = url.host_str().unwrap_or_default();
+            let port = url.port_or_known_default().unwrap_or(3100);
+            let tls = match url.scheme() {
+                "https" => "On",
+                _ => "Off",
+            };
+            // Uses the default push path of Loki unless the URL contains a path.
+            let uri = match url.path() {
+                "" | "/" => String::new(),
+                path => format!("    Uri {path}\n"),
+            };
+            format!(
+                "[OUTPUT]
+    Name loki
+    Match kube.*
+    Host {host}
+    Port {port}
+    Tls {tls}
+{uri}    Labels job=gitserver, namespace=$namespace, component=$component, pod=$pod, container=$container
+    Remove_Keys namespace, component, pod, container
+    Line_Format key_value
+"
+            )
+        }
+        LogSink::Syslog { host, port, tcp } => {
+            let mode = if *tcp { "tcp" } else { "udp" };
+            format!(
+                "[OUTPUT]
+    Name syslog
+    Match kube.*
+    Host {host}
+    Port {port}
+    Mode {mode}
+    Syslog_Format rfc5424
+    Syslog_Message_Key log
+    Syslog_Appname_Key component
+    Syslog_Procid_Key container
+    Syslog_Hostname_Key pod
+"
+            )
+        }
+    };
+    // Lifts the kubernetes metadata to the top level, so every output can use it as label.
+    format!(
+        "[SERVICE]
+    Flush 5
+    Log_Level info
+    Parsers_File /fluent-bit/etc/parsers.conf
+    HTTP_Server On
+    HTTP_Listen 0.0.0.0
+    HTTP_Port {HTTP_PORT}
+    Health_Check On
+
+[INPUT]
+    Name tail
+    Tag kube.*
+    Path /var/log/containers/*_{namespace}_*.log
+    multiline.parser cri, docker
+    DB /var/fluent-bit/state/tail.db
+    Mem_Buf_Limit 10MB
+    Skip_Long_Lines On
+
+[FILTER]
+    Name kubernetes
+    Match kube.*
+    Kube_Tag_Prefix kube.var.log.containers.
+    Labels On
+    Annotations Off
+
+[FILTER]
+    Name nest
+    Match kube.*
+    Operation lift
+    Nested_under kubernetes
+    Add_prefix k8s_
+
+[FILTER]
+    Name nest
+    Match kube.*
+    Operation lift
+    Nested_under k8s_labels
+    Add_prefix label_
+
+[FILTER]
+    Name modify
+    Match kube.*
+    Rename k8s_namespace_name namespace
+    Rename k8s_pod_name pod
+    Rename k8s_container_name container
+    Rename label_app component
+
+[FILTER]
+    Name record_modifier
+    Match kube.*
+    Allowlist_key log
+    Allowlist_key namespace
+    Allowlist_key component
+    Allowlist_key pod
+    Allowlist_key container
+
+{output}"
+    )
+}
@@ -0,0 +1,22 @@
+/// Transport security used to connect to a SMTP server.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MailTls {
+    /// Plain SMTP without encryption.
+    None,
+    /// Upgrade the connection using `STARTTLS`.
+    StartTls,
+    /// Implicit TLS (SMTPS).
+    Tls,
+}
+
+/// Connection information of a SMTP server used to send notifications.
+#[derive(Clone)]
+pub struct MailConfig {
+    pub host: String,
+    pub port: i64,
+    pub tls: MailTls,
+    /// Sender address used for all outgoing mails.
+    pub from: String,
+    pub user: Option<String>,
+    pub password: Option<String>,
+}
@@ -0,0 +1,164 @@
+use std::rc::Rc;
+
+use tf_bindgen::codegen::{resource, Construct};
+use tf_bindgen::{Scope, Value};
+use tf_kubernetes::kubernetes::resource::{kubernetes_deployment, kubernetes_service};
+
+use super::ingress::IngressServiceConfig;
+use super::mail::{MailConfig, MailTls};
+use super::network_policy::NetworkEndpoint;
+use super::security;
+
+const SMTP_PORT: i64 = 1025;
+const HTTP_PORT: i64 = 8025;
+
+/// Catch-all SMTP server used for testing. Every mail sent to this server will be accepted and
+/// can be inspected using the web interface.
+#[derive(Construct)]
+#[construct(builder)]
+pub struct Mailpit {
+    #[construct(id)]
+    name: String,
+    #[construct(scope)]
+    scope: Rc<dyn Scope>,
+    #[construct(setter(into_value))]
+    namespace: Value<String>,
+    #[construct(setter(into))]
+    path: String,
+}
+
+impl Mailpit {
+    /// Returns the SMTP configuration used to send mails to this server.
+    pub fn mail_config(&self, from: impl Into<String>) -> MailConfig {
+        MailConfig {
+            host: format!("{}.{}.svc", self.name, self.namespace.get()),
+            port: SMTP_PORT,
+            tls: MailTls::None,
+            from: from.into(),
+            user: None,
+            password: None,
+        }
+    }
+
+    /// Returns the SMTP server as endpoint of network policies.
+    pub fn endpoint(&self) -> NetworkEndpoint {
+        NetworkEndpoint::app(format!("mailpit-{}", self.name), [SMTP_PORT])
+    }
+
+    pub fn ingress(&self) -> IngressServiceConfig {
+        IngressServiceConfig {
+            rewrite: false,
+            path: self.path.clone(),
+            service_name: self.name.clone(),
+            service_port: HTTP_PORT,
+            pod_labels: self.endpoint().pod_labels,
+            routed_paths: Vec::new(),
+            blocked_paths: Vec::new(),
+        }
+    }
+}
+
+impl MailpitBuilder {
+    pub fn build(&mut self) -> Rc<Mailpit> {
+        let this = Rc::new(Mailpit {
+            name: self.name.clone(),
+            scope: self.scope.clone(),
+            namespace: self.namespace.clone().expect("missing field 'namespace'"),
+            path: self.path.clone().expect("missing field 'path'"),
+        });
+
+        let name = &this.name;
+        let labels = crate::map! {
+            "app" = format!("mailpit-{name}")
+        };
+
+        resource! {
+            &this, resource "kubernetes_service" "mailpit" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+                spec {
+                    selector = &labels
+                    port {
+                        name = "smtp"
+                        port = SMTP_PORT
+                    }
+                    port {
+                        name = "http"
+                        port = HTTP_PORT
+                    }
+                }
+            }
+        };
+
+        resource! {
+            &this, resource "kubernetes_deployment" "mailpit" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+                spec {
+                    replicas = "1"
+                    selector {
+                        match_labels = &labels
+                    }
+                    template {
+                        metadata {
+                            labels = &labels
+                        }
+                        spec {
+                            security_context = security::deployment::pod(1000, 1000)
+                            container {
+                                name = "mailpit"
+                                image = "axllent/mailpit:v1.5.0"
+                                port {
+                                    name = "smtp"
+                                    container_port = SMTP_PORT
+                                }
+                                port {
+                                    name = "http"
+                                    container_port = HTTP_PORT
+                                }
+                                env {
+                                    name = "MP_WEBROOT"
+                                    value = &this.path
+                                }
+                                env {
+                                    name = "MP_SMTP_AUTH_ACCEPT_ANY"
+                                    value = "true"
+                                }
+                                readiness_probe {
+                                    tcp_socket {
+                                        port = "1025"
+                                    }
+                                }
+                                liveness_probe {
+                                    tcp_socket {
+                                        port = "1025"
+                                    }
+                                    success_threshold = 1
+                                    failure_threshold = 10
+                                    period_seconds = 12
+                                    timeout_seconds = 5
+                                }
+                                // Mails are stored in a temporary database.
+                                volume_mount {
+                                    name = "tmp"
+                                    mount_path = "/tmp"
+                                }
+                                security_context = security::deployment::container()
+                            }
+                            volume {
+                                name = "tmp"
+                                empty_dir {}
+                            }
+                        }
+                    }
+                }
+            }
+        };
+
+        this
+    }
+}
@@ -2,7 +2,19 @@ use std::rc::Rc;
 
 use tf_bindgen::codegen::{resource, Construct};
 use tf_bindgen::{Scope, Value};
-use tf_kubernetes::kubernetes::resource::{kubernetes_deployment, kubernetes_service};
+use tf_kubernetes::kubernetes::resource::kubernetes_deployment::{
+    self, KubernetesDeploymentSpecTemplateSpecContainer,
+    KubernetesDeploymentSpecTemplateSpecContainerPort,
+};
+use tf_kubernetes::kubernetes::resource::kubernetes_service;
+
+use super::cache::Cache;
+use super::monitoring::{Monitoring, PodMonitor};
+use super::network_policy::NetworkEndpoint;
+use super::security;
+
+const PORT: i64 = 11211;
+const METRICS_PORT: i64 = 9150;
 
 #[derive(Construct)]
 #[construct(builder)]
@@ -15,6 +27,21 @@ pub struct Memcached {
     namespace: Value<String>,
     #[construct(setter(into_value))]
     memory_limit: Value<String>,
+    /// Adds a `memcached_exporter` sidecar scraped by Prometheus.
+    #[construct(setter(into))]
+    monitoring: Option<Monitoring>,
+}
+
+impl Memcached {
+    /// Returns the configuration used by Gitea to connect to this server.
+    pub fn cache(&self) -> Cache {
+        Cache::Memcached(format!("{}.{}.svc:{PORT}", self.name, self.namespace.get()))
+    }
+
+    /// Returns the server as endpoint of network policies.
+    pub fn endpoint(&self) -> NetworkEndpoint {
+        NetworkEndpoint::app(format!("memcached-{}", self.name), [PORT])
+    }
 }
 
 impl MemcachedBuilder {
@@ -27,6 +54,7 @@ impl MemcachedBuilder {
                 .memory_limit
                 .clone()
                 .expect("missing field 'memory_limit'"),
+            monitoring: self.monitoring.clone().flatten(),
         });
 
         let name = &this.name;
@@ -34,6 +62,29 @@ impl MemcachedBuilder {
             "app" = format!("memcached-{name}")
         };
 
+        let mut sidecars = Vec::new();
+        if let Some(monitoring) = &this.monitoring {
+            let port = KubernetesDeploymentSpecTemplateSpecContainerPort::builder()
+                .name("metrics")
+                .container_port(METRICS_PORT)
+                .build();
+            sidecars.push(
+                KubernetesDeploymentSpecTemplateSpecContainer::builder()
+                    .name("exporter")
+                    .image("prom/memcached-exporter:v0.14.2")
+                    .args(vec![format!("--memcached.address=localhost:{PORT}")])
+                    .port(vec![port])
+                    .security_context(security::deployment::container())
+                    .build(),
+            );
+            PodMonitor::create(&this, format!("{name}-metrics"))
+                .namespace(&this.namespace)
+                .monitoring(monitoring.clone())
+                .pod_labels([("app".to_string(), format!("memcached-{name}"))])
+                .metrics_port(METRICS_PORT)
+                .build();
+        }
+
         resource! {
             &this, resource "kubernetes_service" "memcached" {
                 metadata {
@@ -43,7 +94,7 @@ impl MemcachedBuilder {
                 spec {
                     selector = &labels
                     port {
-                        port = 11211
+                        port = PORT
                     }
                 }
             }
@@ -65,15 +116,16 @@ impl MemcachedBuilder {
                             labels = &labels
                         }
                         spec {
+                            security_context = security::deployment::pod(11211, 11211)
                             container {
                                 name = "memcached"
                                 image = "memcached:1.6.19-alpine"
                                 port {
-                                    container_port = 11211
+                                    container_port = PORT
                                 }
                                 liveness_probe {
                                     tcp_socket {
-                                        port = "11211"
+                                        port = PORT.to_string()
                                     }
                                     success_threshold = 1
                                     failure_threshold = 10
@@ -85,7 +137,9 @@ impl MemcachedBuilder {
                                         "memory" = &this.memory_limit
                                     }
                                 }
+                                security_context = security::deployment::container()
                             }
+                            container = sidecars
                         }
                     }
                 }
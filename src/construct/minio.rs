@@ -0,0 +1,208 @@
+use std::rc::Rc;
+
+use tf_bindgen::codegen::{resource, Construct};
+use tf_bindgen::{Scope, Value};
+use tf_kubernetes::kubernetes::resource::{
+    kubernetes_secret, kubernetes_service, kubernetes_stateful_set,
+};
+
+use super::monitoring::{Monitoring, PodMonitor};
+use super::network_policy::NetworkEndpoint;
+use super::object_storage::ObjectStorage;
+use super::security;
+
+const API_PORT: i64 = 9000;
+
+/// In-cluster [MinIO](https://min.io/) server providing S3-compatible object storage. Buckets are
+/// created by their clients (e.g. Gitea) on first use.
+#[derive(Construct)]
+#[construct(builder)]
+#[allow(dead_code)]
+pub struct Minio {
+    #[construct(id)]
+    name: String,
+    #[construct(scope)]
+    scope: Rc<dyn Scope>,
+    #[construct(setter(into_value))]
+    namespace: Value<String>,
+    #[construct(setter(into_value))]
+    volume_claim: Value<String>,
+    #[construct(setter(into))]
+    access_key: String,
+    /// Generated password of the root user.
+    #[construct(setter(into))]
+    secret_key: String,
+    /// Exposes the cluster metrics without authentication so they can be scraped by Prometheus.
+    #[construct(setter(into))]
+    monitoring: Option<Monitoring>,
+}
+
+impl Minio {
+    /// Returns the connection information of `bucket` stored on this server.
+    pub fn storage(&self, bucket: impl Into<String>) -> ObjectStorage {
+        ObjectStorage {
+            endpoint: format!("{}.{}.svc:{API_PORT}", self.name, self.namespace.get()),
+            bucket: bucket.into(),
+            region: "us-east-1".to_string(),
+            access_key: self.access_key.clone(),
+            secret_key: self.secret_key.clone(),
+            use_ssl: false,
+        }
+    }
+
+    /// Returns the S3 API as endpoint of network policies.
+    pub fn endpoint(&self) -> NetworkEndpoint {
+        NetworkEndpoint::app(format!("minio-{}", self.name), [API_PORT])
+    }
+}
+
+impl MinioBuilder {
+    pub fn build(&mut self) -> Rc<Minio> {
+        let this = Rc::new(Minio {
+            name: self.name.clone(),
+            scope: self.scope.clone(),
+            namespace: self.namespace.clone().expect("missing field 'namespace'"),
+            volume_claim: self
+                .volume_claim
+                .clone()
+                .expect("missing field 'volume_claim'"),
+            access_key: self.access_key.clone().unwrap_or("gitserver".into()),
+            secret_key: self.secret_key.clone().expect("missing field 'secret_key'"),
+            monitoring: self.monitoring.clone().flatten(),
+        });
+
+        let name = &this.name;
+        let labels = crate::map! {
+            "app" = format!("minio-{name}")
+        };
+
+        let prometheus_auth = match &this.monitoring {
+            Some(monitoring) => {
+                PodMonitor::create(&this, format!("{name}-metrics"))
+                    .namespace(&this.namespace)
+                    .monitoring(monitoring.clone())
+                    .pod_labels([("app".to_string(), format!("minio-{name}"))])
+                    .port("api")
+                    .path("/minio/v2/metrics/cluster")
+                    .metrics_port(API_PORT)
+                    .build();
+                "public"
+            }
+            None => "jwt",
+        };
+
+        let checksum = crate::helper::checksum((&this.access_key, &this.secret_key));
+        let credentials = resource! {
+            &this, resource "kubernetes_secret" "minio-credentials" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-credentials")
+                }
+                data = crate::map! {
+                    "MINIO_ROOT_USER" = &this.access_key,
+                    "MINIO_ROOT_PASSWORD" = &this.secret_key
+                }
+            }
+        };
+
+        let service = resource! {
+            &this, resource "kubernetes_service" "minio" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+                spec {
+                    selector = &labels
+                    port {
+                        name = "api"
+                        port = API_PORT
+                    }
+                }
+            }
+        };
+
+        resource! {
+            &this, resource "kubernetes_stateful_set" "minio" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+                spec {
+                    replicas = "1"
+                    service_name = &service.metadata[0].name
+                    selector {
+                        match_labels = &labels
+                    }
+                    template {
+                        metadata {
+                            labels = &labels
+                            annotations = crate::map! {
+                                "gitserver/checksum" = checksum
+                            }
+                        }
+                        spec {
+                            security_context = security::stateful_set::pod(1000, 1000)
+                            container {
+                                name = "minio"
+                                image = "minio/minio:RELEASE.2023-11-20T22-40-07Z"
+                                // The default certificate directory is located in the home
+                                // directory, which is not writable.
+                                args = ["server", "/data", "--certs-dir", "/tmp/certs"]
+                                port {
+                                    name = "api"
+                                    container_port = API_PORT
+                                }
+                                env {
+                                    name = "MINIO_PROMETHEUS_AUTH_TYPE"
+                                    value = prometheus_auth
+                                }
+                                env_from {
+                                    secret_ref {
+                                        name = &credentials.metadata[0].name
+                                    }
+                                }
+                                volume_mount {
+                                    name = "data"
+                                    mount_path = "/data"
+                                }
+                                volume_mount {
+                                    name = "tmp"
+                                    mount_path = "/tmp"
+                                }
+                                readiness_probe {
+                                    http_get {
+                                        path = "/minio/health/ready"
+                                        port = "api"
+                                    }
+                                }
+                                liveness_probe {
+                                    http_get {
+                                        path = "/minio/health/live"
+                                        port = "api"
+                                    }
+                                    success_threshold = 1
+                                    failure_threshold = 10
+                                    period_seconds = 12
+                                    timeout_seconds = 5
+                                }
+                                security_context = security::stateful_set::container()
+                            }
+                            volume {
+                                name = "data"
+                                persistent_volume_claim {
+                                    claim_name = &this.volume_claim
+                                }
+                            }
+                            volume {
+                                name = "tmp"
+                                empty_dir {}
+                            }
+                        }
+                    }
+                }
+            }
+        };
+
+        this
+    }
+}
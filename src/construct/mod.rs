@@ -1,7 +1,30 @@
+pub mod alertmanager;
+pub mod cache;
+pub mod ci;
+pub mod dex;
+pub mod dind;
+pub mod elasticsearch;
 pub mod gitea;
+pub mod gitea_auth;
+pub mod gitea_bootstrap;
+pub mod gitea_runner;
+pub mod grafana;
+pub mod indexer;
 pub mod ingress;
 pub mod jenkins;
 pub mod local_dir_volume;
 pub mod local_dir_volume_claim;
+pub mod log_collector;
+pub mod mail;
+pub mod mailpit;
 pub mod memcached;
+pub mod minio;
+pub mod monitoring;
+pub mod network_policy;
+pub mod object_storage;
 pub mod postgres;
+pub mod prometheus;
+pub mod redis;
+pub mod security;
+pub mod shared_volume_claim;
+pub mod woodpecker;
@@ -0,0 +1,174 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use tf_bindgen::codegen::{resource, Construct};
+use tf_bindgen::json;
+use tf_bindgen::{Scope, Value};
+use tf_kubernetes::kubernetes::resource::kubernetes_manifest;
+
+use super::network_policy::{NetworkEndpoint, NetworkPeer, NetworkPolicy};
+
+/// Prometheus settings shared by all constructs exposing metrics. Requires the `PodMonitor`
+/// custom resource definition of the Prometheus Operator unless `scrape_jobs` is set.
+#[derive(Clone)]
+pub struct Monitoring {
+    /// Pods running Prometheus. Metrics ports accept connections from these pods.
+    pub prometheus: NetworkPeer,
+    /// Labels added to all pod monitors. Used by the `podMonitorSelector` of Prometheus.
+    pub labels: HashMap<String, String>,
+    /// Scrape jobs of the bundled Prometheus. Pod monitors register their job here instead of
+    /// creating a `PodMonitor` resource if set.
+    pub scrape_jobs: Option<ScrapeJobs>,
+}
+
+/// Scrape jobs registered by pod monitors. Shared by all clones of [`Monitoring`]. The bundled
+/// Prometheus takes the jobs once all pod monitors were created (see [`ScrapeJobs::take`]).
+#[derive(Clone, Default)]
+pub struct ScrapeJobs {
+    jobs: Rc<RefCell<Vec<ScrapeJob>>>,
+    taken: Rc<Cell<bool>>,
+}
+
+impl ScrapeJobs {
+    /// Registers `job`.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the jobs were already taken, since the bundled Prometheus would not scrape
+    /// `job`. Pod monitors must be created before Prometheus.
+    pub fn push(&self, job: ScrapeJob) {
+        assert!(
+            !self.taken.get(),
+            "pod monitor '{}' created after the bundled Prometheus",
+            job.name
+        );
+        self.jobs.borrow_mut().push(job);
+    }
+
+    /// Returns the registered jobs. Registering further jobs will panic.
+    pub fn take(&self) -> Vec<ScrapeJob> {
+        self.taken.set(true);
+        self.jobs.take()
+    }
+}
+
+/// Endpoint scraped by the bundled Prometheus. Equivalent of a `PodMonitor` resource.
+#[derive(Clone)]
+pub struct ScrapeJob {
+    pub name: String,
+    pub namespace: String,
+    pub pod_labels: HashMap<String, String>,
+    pub port: String,
+    pub path: String,
+    pub token: Option<String>,
+}
+
+/// Bearer token required to scrape an endpoint and the secret key storing it.
+#[derive(Clone)]
+pub struct MetricsToken {
+    pub secret_name: String,
+    pub key: String,
+    pub value: String,
+}
+
+/// `PodMonitor` scraping the container port `port` of all pods matching `pod_labels`. A network
+/// policy allows Prometheus to connect to this port (`metrics_port`).
+#[derive(Construct)]
+#[construct(builder)]
+#[allow(dead_code)]
+pub struct PodMonitor {
+    #[construct(id)]
+    name: String,
+    #[construct(scope)]
+    scope: Rc<dyn Scope>,
+    #[construct(setter(into_value))]
+    namespace: Value<String>,
+    #[construct(setter(into))]
+    monitoring: Monitoring,
+    #[construct(setter(into))]
+    pod_labels: HashMap<String, String>,
+    /// Name of the container port serving metrics.
+    #[construct(setter(into))]
+    port: String,
+    #[construct(setter(into))]
+    path: String,
+    #[construct(setter(into))]
+    token: Option<MetricsToken>,
+    /// Port number of `port`.
+    #[construct(setter(into))]
+    metrics_port: i64,
+}
+
+impl PodMonitorBuilder {
+    pub fn build(&mut self) -> Rc<PodMonitor> {
+        let this = Rc::new(PodMonitor {
+            name: self.name.clone(),
+            scope: self.scope.clone(),
+            namespace: self.namespace.clone().expect("missing field 'namespace'"),
+            monitoring: self.monitoring.clone().expect("missing field 'monitoring'"),
+            pod_labels: self.pod_labels.clone().expect("missing field 'pod_labels'"),
+            port: self.port.clone().unwrap_or("metrics".into()),
+            path: self.path.clone().unwrap_or("/metrics".into()),
+            token: self.token.clone().flatten(),
+            metrics_port: self.metrics_port.expect("missing field 'metrics_port'"),
+        });
+
+        let name = &this.name;
+        if let Some(scrape_jobs) = &this.monitoring.scrape_jobs {
+            scrape_jobs.push(ScrapeJob {
+                name: name.clone(),
+                namespace: this.namespace.get().to_string(),
+                pod_labels: this.pod_labels.clone(),
+                port: this.port.clone(),
+                path: this.path.clone(),
+                token: this.token.as_ref().map(|token| token.value.clone()),
+            });
+        } else {
+            build_pod_monitor(Rc::clone(&this));
+        }
+
+        NetworkPolicy::create(&this, format!("{name}-metrics"))
+            .namespace(&this.namespace)
+            .endpoint(NetworkEndpoint {
+                pod_labels: this.pod_labels.clone(),
+                ports: vec![this.metrics_port],
+            })
+            .from(vec![this.monitoring.prometheus.clone()])
+            .build();
+
+        this
+    }
+}
+
+/// Creates the `PodMonitor` resource scraped by the Prometheus Operator.
+fn build_pod_monitor(this: Rc<PodMonitor>) {
+    let mut endpoint = json::json!({
+        "port": &this.port,
+        "path": &this.path
+    });
+    if let Some(token) = &this.token {
+        endpoint["authorization"] = json::json!({
+            "type": "Bearer",
+            "credentials": { "name": &token.secret_name, "key": &token.key }
+        });
+    }
+    let manifest = json::json!({
+        "apiVersion": "monitoring.coreos.com/v1",
+        "kind": "PodMonitor",
+        "metadata": {
+            "namespace": this.namespace.get().as_str(),
+            "name": &this.name,
+            "labels": &this.monitoring.labels
+        },
+        "spec": {
+            "selector": { "matchLabels": &this.pod_labels },
+            "podMetricsEndpoints": [endpoint]
+        }
+    });
+    resource! {
+        &this, resource "kubernetes_manifest" "pod-monitor" {
+            manifest = manifest
+        }
+    };
+}
@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use tf_bindgen::codegen::{resource, Construct};
+use tf_bindgen::value::IntoValue;
+use tf_bindgen::{Scope, Value};
+use tf_kubernetes::kubernetes::resource::kubernetes_network_policy::{
+    self, KubernetesNetworkPolicySpecIngress, KubernetesNetworkPolicySpecIngressFrom,
+    KubernetesNetworkPolicySpecIngressFromIpBlock,
+    KubernetesNetworkPolicySpecIngressFromNamespaceSelector,
+    KubernetesNetworkPolicySpecIngressFromPodSelector, KubernetesNetworkPolicySpecIngressPorts,
+};
+
+/// Source of connections allowed by a [`NetworkPolicy`].
+#[derive(Clone)]
+pub enum NetworkPeer {
+    /// Pods of the same namespace matching these labels.
+    Pods(HashMap<String, String>),
+    /// Pods of namespace `name` matching `labels`. Selects all pods of the namespace if `labels`
+    /// is empty.
+    Namespace {
+        name: String,
+        labels: HashMap<String, String>,
+    },
+    /// IP range in CIDR notation (e.g. `10.0.0.0/8`).
+    IpBlock(String),
+}
+
+impl NetworkPeer {
+    /// Returns the peer selecting the pods of the same namespace with `app` label `app`.
+    pub fn app(app: impl Into<String>) -> Self {
+        Self::Pods(HashMap::from([("app".to_string(), app.into())]))
+    }
+
+    /// Returns the peer selecting the pods of job `job` in the same namespace.
+    pub fn job(job: impl Into<String>) -> Self {
+        Self::Pods(HashMap::from([("job-name".to_string(), job.into())]))
+    }
+
+    /// Returns the same peer selecting pods of namespace `namespace` instead. Used for peers
+    /// running in another namespace.
+    pub fn in_namespace(self, namespace: impl Into<String>) -> Self {
+        match self {
+            NetworkPeer::Pods(labels) | NetworkPeer::Namespace { labels, .. } => {
+                NetworkPeer::Namespace {
+                    name: namespace.into(),
+                    labels,
+                }
+            }
+            NetworkPeer::IpBlock(_) => self,
+        }
+    }
+
+    fn to_builder(&self) -> KubernetesNetworkPolicySpecIngressFrom {
+        let pods = |labels: &HashMap<String, String>| {
+            KubernetesNetworkPolicySpecIngressFromPodSelector::builder()
+                .match_labels(labels)
+                .build()
+        };
+        match self {
+            NetworkPeer::Pods(labels) => KubernetesNetworkPolicySpecIngressFrom::builder()
+                .pod_selector(pods(labels))
+                .build(),
+            NetworkPeer::Namespace { name, labels } => {
+                let namespace = KubernetesNetworkPolicySpecIngressFromNamespaceSelector::builder()
+                    .match_labels(crate::map! {
+                        "kubernetes.io/metadata.name" = name
+                    })
+                    .build();
+                let mut from = KubernetesNetworkPolicySpecIngressFrom::builder();
+                from.namespace_selector(namespace);
+                if !labels.is_empty() {
+                    from.pod_selector(pods(labels));
+                }
+                from.build()
+            }
+            NetworkPeer::IpBlock(cidr) => {
+                let ip_block = KubernetesNetworkPolicySpecIngressFromIpBlock::builder()
+                    .cidr(cidr)
+                    .build();
+                KubernetesNetworkPolicySpecIngressFrom::builder()
+                    .ip_block(ip_block)
+                    .build()
+            }
+        }
+    }
+}
+
+/// Pods and ports a client connects to. Returned by the constructs serving other components.
+#[derive(Clone, Default)]
+pub struct NetworkEndpoint {
+    /// Labels of the pods. Selects all pods of the namespace if empty.
+    pub pod_labels: HashMap<String, String>,
+    /// Container ports. Includes all ports if empty.
+    pub ports: Vec<i64>,
+}
+
+impl NetworkEndpoint {
+    /// Returns the endpoint selecting the pods with `app` label `app`.
+    pub fn app(app: impl Into<String>, ports: impl Into<Vec<i64>>) -> Self {
+        Self {
+            pod_labels: HashMap::from([("app".to_string(), app.into())]),
+            ports: ports.into(),
+        }
+    }
+}
+
+/// Endpoints of the components and the peers allowed to connect to them. Servers are usually
+/// created before their clients, so the network policies are created once all clients are known
+/// (see [`NetworkClients::build`]).
+#[derive(Default)]
+pub struct NetworkClients {
+    components: HashMap<String, (NetworkEndpoint, Vec<NetworkPeer>)>,
+}
+
+impl NetworkClients {
+    /// Registers the endpoint of `component`.
+    pub fn endpoint(&mut self, component: impl Into<String>, endpoint: NetworkEndpoint) {
+        self.components
+            .insert(component.into(), (endpoint, Vec::new()));
+    }
+
+    pub fn contains(&self, component: &str) -> bool {
+        self.components.contains_key(component)
+    }
+
+    /// Returns the sorted names of the registered components.
+    pub fn components(&self) -> Vec<&str> {
+        let mut components: Vec<_> = self.components.keys().map(String::as_str).collect();
+        components.sort_unstable();
+        components
+    }
+
+    /// Allows `peer` to connect to the endpoint of `component`.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if no endpoint was registered for `component`.
+    pub fn allow(&mut self, component: &str, peer: NetworkPeer) {
+        let (_, peers) = self
+            .components
+            .get_mut(component)
+            .unwrap_or_else(|| panic!("unknown component '{component}'"));
+        peers.push(peer);
+    }
+
+    /// Creates a network policy `{component}-clients` for every component with clients.
+    pub fn build<C: Scope + 'static>(&self, scope: &Rc<C>, namespace: impl IntoValue<String>) {
+        let namespace = namespace.into_value();
+        for (component, (endpoint, peers)) in &self.components {
+            if peers.is_empty() {
+                continue;
+            }
+            NetworkPolicy::create(scope, format!("{component}-clients"))
+                .namespace(&namespace)
+                .endpoint(endpoint.clone())
+                .from(peers.clone())
+                .build();
+        }
+    }
+}
+
+/// Network policy allowing connections from `from` to `endpoint`. Pods selected by any network
+/// policy reject all connections not allowed by one of them, so a policy without sources denies
+/// all incoming connections to `endpoint`.
+#[derive(Construct)]
+#[construct(builder)]
+#[allow(dead_code)]
+pub struct NetworkPolicy {
+    #[construct(id)]
+    name: String,
+    #[construct(scope)]
+    scope: Rc<dyn Scope>,
+    #[construct(setter(into_value))]
+    namespace: Value<String>,
+    /// Selects all pods and ports of the namespace if not set.
+    #[construct(setter(into))]
+    endpoint: NetworkEndpoint,
+    #[construct(setter(into))]
+    from: Vec<NetworkPeer>,
+}
+
+impl NetworkPolicyBuilder {
+    pub fn build(&mut self) -> Rc<NetworkPolicy> {
+        let this = Rc::new(NetworkPolicy {
+            name: self.name.clone(),
+            scope: self.scope.clone(),
+            namespace: self.namespace.clone().expect("missing field 'namespace'"),
+            endpoint: self.endpoint.clone().unwrap_or_default(),
+            from: self.from.clone().unwrap_or_default(),
+        });
+
+        let name = &this.name;
+        let mut rules = Vec::new();
+        if !this.from.is_empty() {
+            let mut rule = KubernetesNetworkPolicySpecIngress::builder();
+            rule.from(
+                this.from
+                    .iter()
+                    .map(NetworkPeer::to_builder)
+                    .collect::<Vec<_>>(),
+            );
+            if !this.endpoint.ports.is_empty() {
+                let ports: Vec<_> = this
+                    .endpoint
+                    .ports
+                    .iter()
+                    .map(|port| {
+                        KubernetesNetworkPolicySpecIngressPorts::builder()
+                            .port(port.to_string())
+                            .protocol("TCP")
+                            .build()
+                    })
+                    .collect();
+                rule.ports(ports);
+            }
+            rules.push(rule.build());
+        }
+        resource! {
+            &this, resource "kubernetes_network_policy" "policy" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+                spec {
+                    pod_selector {
+                        match_labels = &this.endpoint.pod_labels
+                    }
+                    policy_types = ["Ingress"]
+                    ingress = rules
+                }
+            }
+        };
+
+        this
+    }
+}
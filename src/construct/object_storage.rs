@@ -0,0 +1,11 @@
+/// Connection information of an S3-compatible bucket.
+#[derive(Clone)]
+pub struct ObjectStorage {
+    /// Host and port of the S3 API, e.g. `s3.amazonaws.com`.
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+    pub use_ssl: bool,
+}
@@ -0,0 +1,448 @@
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+use tf_bindgen::codegen::{resource, Construct};
+use tf_bindgen::json;
+use tf_bindgen::{Scope, Value};
+use tf_kubernetes::kubernetes::resource::{
+    kubernetes_cluster_role, kubernetes_cluster_role_binding, kubernetes_config_map,
+    kubernetes_role, kubernetes_role_binding, kubernetes_secret, kubernetes_service,
+    kubernetes_service_account, kubernetes_stateful_set,
+};
+
+use super::monitoring::ScrapeJob;
+use super::network_policy::{NetworkEndpoint, NetworkPeer};
+use super::security;
+
+const ALERT_RULES: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/script/prometheus/alerts.yml"
+));
+
+const HTTP_PORT: i64 = 9090;
+const SERVICE_ACCOUNT_DIR: &str = "/var/run/secrets/kubernetes.io/serviceaccount";
+
+/// [Prometheus](https://prometheus.io/) server scraping the jobs registered by pod monitors.
+/// Volume usage and probe results are read from the kubelets of all nodes. Alerts are evaluated
+/// using the rules of `script/prometheus/alerts.yml` and sent to `alertmanager`.
+#[derive(Construct)]
+#[construct(builder)]
+#[allow(dead_code)]
+pub struct Prometheus {
+    #[construct(id)]
+    name: String,
+    #[construct(scope)]
+    scope: Rc<dyn Scope>,
+    #[construct(setter(into_value))]
+    namespace: Value<String>,
+    #[construct(setter(into_value))]
+    volume_claim: Value<String>,
+    #[construct(setter(into))]
+    scrape_jobs: Vec<ScrapeJob>,
+    /// Address of Alertmanager (e.g. `alertmanager.gitserver.svc:9093`).
+    #[construct(setter(into))]
+    alertmanager: Option<String>,
+    /// Time to keep samples (e.g. `15d`).
+    #[construct(setter(into))]
+    retention: String,
+}
+
+impl Prometheus {
+    /// Returns the in-cluster URL of the HTTP API.
+    pub fn url(&self) -> String {
+        format!(
+            "http://{}.{}.svc:{HTTP_PORT}",
+            self.name,
+            self.namespace.get()
+        )
+    }
+
+    /// Returns the HTTP API as endpoint of network policies.
+    pub fn endpoint(&self) -> NetworkEndpoint {
+        NetworkEndpoint::app(format!("prometheus-{}", self.name), [HTTP_PORT])
+    }
+
+    /// Returns the Prometheus pods as source of network policies.
+    pub fn peer(&self) -> NetworkPeer {
+        NetworkPeer::app(format!("prometheus-{}", self.name))
+    }
+}
+
+impl PrometheusBuilder {
+    pub fn build(&mut self) -> Rc<Prometheus> {
+        let this = Rc::new(Prometheus {
+            name: self.name.clone(),
+            scope: self.scope.clone(),
+            namespace: self.namespace.clone().expect("missing field 'namespace'"),
+            volume_claim: self
+                .volume_claim
+                .clone()
+                .expect("missing field 'volume_claim'"),
+            scrape_jobs: self.scrape_jobs.clone().unwrap_or_default(),
+            alertmanager: self.alertmanager.clone().flatten(),
+            retention: self.retention.clone().unwrap_or("15d".into()),
+        });
+
+        let name = &this.name;
+        let namespace = this.namespace.get();
+        let labels = crate::map! {
+            "app" = format!("prometheus-{name}")
+        };
+
+        // Service discovery of pods is restricted to the namespaces of the scrape jobs, while the
+        // kubelets are scraped through the API server proxy.
+        let cluster_role = resource! {
+            &this, resource "kubernetes_cluster_role" "prometheus" {
+                metadata {
+                    name = format!("{namespace}-{name}")
+                }
+                rule {
+                    api_groups = [""]
+                    resources = ["nodes", "nodes/metrics", "nodes/proxy"]
+                    verbs = ["get", "list", "watch"]
+                }
+            }
+        };
+        let service_account = resource! {
+            &this, resource "kubernetes_service_account" "prometheus" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+            }
+        };
+        let namespaces: BTreeSet<_> = this
+            .scrape_jobs
+            .iter()
+            .map(|job| job.namespace.clone())
+            .chain([namespace.to_string()])
+            .collect();
+        for discovered in namespaces {
+            PodDiscovery::create(&this, format!("{name}-{discovered}"))
+                .namespace(discovered)
+                .service_account(&service_account.metadata[0].name)
+                .service_account_namespace(&this.namespace)
+                .build();
+        }
+        resource! {
+            &this, resource "kubernetes_cluster_role_binding" "prometheus" {
+                metadata {
+                    name = format!("{namespace}-{name}")
+                }
+                role_ref {
+                    api_group = "rbac.authorization.k8s.io"
+                    kind = "ClusterRole"
+                    name = &cluster_role.metadata[0].name
+                }
+                subject {
+                    kind = "ServiceAccount"
+                    name = &service_account.metadata[0].name
+                    namespace = &this.namespace
+                }
+            }
+        };
+
+        let mut scrape_configs: Vec<_> = this.scrape_jobs.iter().map(scrape_config).collect();
+        scrape_configs.push(kubelet_scrape_config("kubelet", "metrics", &namespace));
+        scrape_configs.push(kubelet_scrape_config(
+            "kubelet-probes",
+            "metrics/probes",
+            &namespace,
+        ));
+        let alertmanagers: Vec<_> = this
+            .alertmanager
+            .iter()
+            .map(|address| json::json!({ "static_configs": [{ "targets": [address] }] }))
+            .collect();
+        let config = json::json!({
+            "global": {
+                "scrape_interval": "30s",
+                "evaluation_interval": "30s"
+            },
+            "rule_files": ["/etc/prometheus/rules/*.yml"],
+            "alerting": { "alertmanagers": alertmanagers },
+            "scrape_configs": scrape_configs
+        });
+        let config = crate::helper::yaml(&config);
+        let checksum = crate::helper::checksum((&config, ALERT_RULES));
+        // Stored as secret, since scrape jobs may contain bearer tokens.
+        let config = resource! {
+            &this, resource "kubernetes_secret" "prometheus-config" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-config")
+                }
+                data = crate::map! {
+                    "prometheus.yml" = config
+                }
+            }
+        };
+        let rules = resource! {
+            &this, resource "kubernetes_config_map" "prometheus-rules" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-rules")
+                }
+                data = crate::map! {
+                    "alerts.yml" = ALERT_RULES
+                }
+            }
+        };
+
+        let service = resource! {
+            &this, resource "kubernetes_service" "prometheus" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+                spec {
+                    selector = &labels
+                    port {
+                        name = "http"
+                        port = HTTP_PORT
+                    }
+                }
+            }
+        };
+
+        resource! {
+            &this, resource "kubernetes_stateful_set" "prometheus" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+                spec {
+                    replicas = "1"
+                    service_name = &service.metadata[0].name
+                    selector {
+                        match_labels = &labels
+                    }
+                    template {
+                        metadata {
+                            labels = &labels
+                            annotations = crate::map! {
+                                "gitserver/checksum" = checksum
+                            }
+                        }
+                        spec {
+                            service_account_name = &service_account.metadata[0].name
+                            security_context = security::stateful_set::pod(65534, 65534)
+                            container {
+                                name = "prometheus"
+                                image = "prom/prometheus:v2.48.0"
+                                args = [
+                                    "--config.file=/etc/prometheus/config/prometheus.yml",
+                                    "--storage.tsdb.path=/prometheus",
+                                    format!("--storage.tsdb.retention.time={}", this.retention)
+                                ]
+                                port {
+                                    name = "http"
+                                    container_port = HTTP_PORT
+                                }
+                                volume_mount {
+                                    name = "config"
+                                    mount_path = "/etc/prometheus/config"
+                                }
+                                volume_mount {
+                                    name = "rules"
+                                    mount_path = "/etc/prometheus/rules"
+                                }
+                                volume_mount {
+                                    name = "data"
+                                    mount_path = "/prometheus"
+                                }
+                                readiness_probe {
+                                    http_get {
+                                        path = "/-/ready"
+                                        port = "http"
+                                    }
+                                }
+                                liveness_probe {
+                                    http_get {
+                                        path = "/-/healthy"
+                                        port = "http"
+                                    }
+                                    success_threshold = 1
+                                    failure_threshold = 10
+                                    period_seconds = 12
+                                    timeout_seconds = 5
+                                }
+                                security_context = security::stateful_set::container()
+                            }
+                            volume {
+                                name = "config"
+                                secret {
+                                    secret_name = &config.metadata[0].name
+                                }
+                            }
+                            volume {
+                                name = "rules"
+                                config_map {
+                                    name = &rules.metadata[0].name
+                                }
+                            }
+                            volume {
+                                name = "data"
+                                persistent_volume_claim {
+                                    claim_name = &this.volume_claim
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        };
+
+        this
+    }
+}
+
+/// Role and binding allowing the service account of Prometheus to discover the pods of
+/// `namespace`.
+#[derive(Construct)]
+#[construct(builder)]
+#[allow(dead_code)]
+pub struct PodDiscovery {
+    #[construct(id)]
+    name: String,
+    #[construct(scope)]
+    scope: Rc<dyn Scope>,
+    #[construct(setter(into_value))]
+    namespace: Value<String>,
+    #[construct(setter(into_value))]
+    service_account: Value<String>,
+    #[construct(setter(into_value))]
+    service_account_namespace: Value<String>,
+}
+
+impl PodDiscoveryBuilder {
+    pub fn build(&mut self) -> Rc<PodDiscovery> {
+        let this = Rc::new(PodDiscovery {
+            name: self.name.clone(),
+            scope: self.scope.clone(),
+            namespace: self.namespace.clone().expect("missing field 'namespace'"),
+            service_account: self
+                .service_account
+                .clone()
+                .expect("missing field 'service_account'"),
+            service_account_namespace: self
+                .service_account_namespace
+                .clone()
+                .expect("missing field 'service_account_namespace'"),
+        });
+
+        let role = resource! {
+            &this, resource "kubernetes_role" "pod-discovery" {
+                metadata {
+                    namespace = &this.namespace
+                    name = &this.service_account
+                }
+                rule {
+                    api_groups = [""]
+                    resources = ["pods"]
+                    verbs = ["get", "list", "watch"]
+                }
+            }
+        };
+        resource! {
+            &this, resource "kubernetes_role_binding" "pod-discovery" {
+                metadata {
+                    namespace = &this.namespace
+                    name = &this.service_account
+                }
+                role_ref {
+                    api_group = "rbac.authorization.k8s.io"
+                    kind = "Role"
+                    name = &role.metadata[0].name
+                }
+                subject {
+                    kind = "ServiceAccount"
+                    name = &this.service_account
+                    namespace = &this.service_account_namespace
+                }
+            }
+        };
+
+        this
+    }
+}
+
+/// Returns the scrape configuration of `job` selecting the pods using Kubernetes service
+/// discovery.
+fn scrape_config(job: &ScrapeJob) -> json::Value {
+    let mut relabel_configs: Vec<_> = job
+        .pod_labels
+        .iter()
+        .map(|(label, value)| {
+            let label: String = label
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                .collect();
+            json::json!({
+                "source_labels": [format!("__meta_kubernetes_pod_label_{label}")],
+                "regex": value,
+                "action": "keep"
+            })
+        })
+        .collect();
+    relabel_configs.extend([
+        json::json!({
+            "source_labels": ["__meta_kubernetes_pod_container_port_name"],
+            "regex": &job.port,
+            "action": "keep"
+        }),
+        json::json!({
+            "source_labels": ["__meta_kubernetes_namespace"],
+            "target_label": "namespace"
+        }),
+        json::json!({
+            "source_labels": ["__meta_kubernetes_pod_name"],
+            "target_label": "pod"
+        }),
+    ]);
+    let mut config = json::json!({
+        "job_name": &job.name,
+        "metrics_path": &job.path,
+        "kubernetes_sd_configs": [{
+            "role": "pod",
+            "namespaces": { "names": [&job.namespace] }
+        }],
+        "relabel_configs": relabel_configs
+    });
+    if let Some(token) = &job.token {
+        config["authorization"] = json::json!({ "credentials": token });
+    }
+    config
+}
+
+/// Returns the scrape configuration of the kubelet endpoint `path` of all nodes. Only keeps
+/// metrics of `namespace`.
+fn kubelet_scrape_config(name: &str, path: &str, namespace: &str) -> json::Value {
+    json::json!({
+        "job_name": name,
+        "scheme": "https",
+        "kubernetes_sd_configs": [{ "role": "node" }],
+        "tls_config": { "ca_file": format!("{SERVICE_ACCOUNT_DIR}/ca.crt") },
+        "authorization": { "credentials_file": format!("{SERVICE_ACCOUNT_DIR}/token") },
+        "relabel_configs": [
+            {
+                "target_label": "__address__",
+                "replacement": "kubernetes.default.svc:443"
+            },
+            {
+                "source_labels": ["__meta_kubernetes_node_name"],
+                "target_label": "__metrics_path__",
+                "replacement": format!("/api/v1/nodes/$1/proxy/{path}")
+            },
+            {
+                "source_labels": ["__meta_kubernetes_node_name"],
+                "target_label": "node"
+            }
+        ],
+        "metric_relabel_configs": [{
+            "source_labels": ["namespace"],
+            "regex": namespace,
+            "action": "keep"
+        }]
+    })
+}
@@ -0,0 +1,221 @@
+use std::rc::Rc;
+
+use tf_bindgen::codegen::{resource, Construct};
+use tf_bindgen::{Scope, Value};
+use tf_kubernetes::kubernetes::resource::kubernetes_stateful_set::{
+    self, KubernetesStatefulSetSpecTemplateSpecContainer,
+    KubernetesStatefulSetSpecTemplateSpecContainerEnvFrom,
+    KubernetesStatefulSetSpecTemplateSpecContainerEnvFromSecretRef,
+    KubernetesStatefulSetSpecTemplateSpecContainerPort,
+};
+use tf_kubernetes::kubernetes::resource::{kubernetes_secret, kubernetes_service};
+use url::Url;
+
+use super::cache::Cache;
+use super::monitoring::{Monitoring, PodMonitor};
+use super::network_policy::NetworkEndpoint;
+use super::security;
+
+const PORT: i64 = 6379;
+const METRICS_PORT: i64 = 9121;
+
+/// [Redis](https://redis.io/) server used by Gitea as cache, session and queue backend. Data is
+/// persisted using an append only file, so queued tasks survive restarts.
+#[derive(Construct)]
+#[construct(builder)]
+#[allow(dead_code)]
+pub struct Redis {
+    #[construct(id)]
+    name: String,
+    #[construct(scope)]
+    scope: Rc<dyn Scope>,
+    #[construct(setter(into_value))]
+    namespace: Value<String>,
+    #[construct(setter(into_value))]
+    volume_claim: Value<String>,
+    /// Password required to connect to the server.
+    #[construct(setter(into))]
+    password: String,
+    #[construct(setter(into_value))]
+    memory_limit: Value<String>,
+    /// Adds a `redis_exporter` sidecar scraped by Prometheus.
+    #[construct(setter(into))]
+    monitoring: Option<Monitoring>,
+}
+
+impl Redis {
+    /// Returns the configuration used by Gitea to connect to this server. The password is
+    /// percent-encoded, since it may contain reserved characters.
+    pub fn cache(&self) -> Cache {
+        let mut url = Url::parse(&format!(
+            "redis://{}.{}.svc:{PORT}",
+            self.name,
+            self.namespace.get()
+        ))
+        .expect("valid redis url");
+        url.set_password(Some(&self.password))
+            .expect("redis url with host");
+        Cache::Redis(url.to_string())
+    }
+
+    /// Returns the server as endpoint of network policies.
+    pub fn endpoint(&self) -> NetworkEndpoint {
+        NetworkEndpoint::app(format!("redis-{}", self.name), [PORT])
+    }
+}
+
+impl RedisBuilder {
+    pub fn build(&mut self) -> Rc<Redis> {
+        let this = Rc::new(Redis {
+            name: self.name.clone(),
+            scope: self.scope.clone(),
+            namespace: self.namespace.clone().expect("missing field 'namespace'"),
+            volume_claim: self
+                .volume_claim
+                .clone()
+                .expect("missing field 'volume_claim'"),
+            password: self.password.clone().expect("missing field 'password'"),
+            memory_limit: self
+                .memory_limit
+                .clone()
+                .expect("missing field 'memory_limit'"),
+            monitoring: self.monitoring.clone().flatten(),
+        });
+
+        let name = &this.name;
+        let labels = crate::map! {
+            "app" = format!("redis-{name}")
+        };
+
+        let checksum = crate::helper::checksum(&this.password);
+        let credentials = resource! {
+            &this, resource "kubernetes_secret" "redis-credentials" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-credentials")
+                }
+                data = crate::map! {
+                    "REDIS_PASSWORD" = &this.password
+                }
+            }
+        };
+
+        // The exporter reads the password from `REDIS_PASSWORD` as well.
+        let mut sidecars = Vec::new();
+        if let Some(monitoring) = &this.monitoring {
+            let port = KubernetesStatefulSetSpecTemplateSpecContainerPort::builder()
+                .name("metrics")
+                .container_port(METRICS_PORT)
+                .build();
+            let secret_ref =
+                KubernetesStatefulSetSpecTemplateSpecContainerEnvFromSecretRef::builder()
+                    .name(&credentials.metadata[0].name)
+                    .build();
+            let env_from = KubernetesStatefulSetSpecTemplateSpecContainerEnvFrom::builder()
+                .secret_ref(secret_ref)
+                .build();
+            sidecars.push(
+                KubernetesStatefulSetSpecTemplateSpecContainer::builder()
+                    .name("exporter")
+                    .image("oliver006/redis_exporter:v1.55.0")
+                    .args(vec![format!("--redis.addr=redis://localhost:{PORT}")])
+                    .env_from(vec![env_from])
+                    .port(vec![port])
+                    .security_context(security::stateful_set::container())
+                    .build(),
+            );
+            PodMonitor::create(&this, format!("{name}-metrics"))
+                .namespace(&this.namespace)
+                .monitoring(monitoring.clone())
+                .pod_labels([("app".to_string(), format!("redis-{name}"))])
+                .metrics_port(METRICS_PORT)
+                .build();
+        }
+
+        let service = resource! {
+            &this, resource "kubernetes_service" "redis" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+                spec {
+                    selector = &labels
+                    port {
+                        name = "redis"
+                        port = PORT
+                    }
+                }
+            }
+        };
+
+        resource! {
+            &this, resource "kubernetes_stateful_set" "redis" {
+                metadata {
+                    namespace = &this.namespace
+                    name = name
+                }
+                spec {
+                    replicas = "1"
+                    service_name = &service.metadata[0].name
+                    selector {
+                        match_labels = &labels
+                    }
+                    template {
+                        metadata {
+                            labels = &labels
+                            annotations = crate::map! {
+                                "gitserver/checksum" = checksum
+                            }
+                        }
+                        spec {
+                            security_context = security::stateful_set::pod(999, 1000)
+                            container {
+                                name = "redis"
+                                image = "redis:7.2.3-alpine"
+                                // `$(REDIS_PASSWORD)` is expanded by Kubernetes.
+                                args = ["--requirepass", "$(REDIS_PASSWORD)", "--appendonly", "yes"]
+                                port {
+                                    name = "redis"
+                                    container_port = PORT
+                                }
+                                env_from {
+                                    secret_ref {
+                                        name = &credentials.metadata[0].name
+                                    }
+                                }
+                                volume_mount {
+                                    name = "data"
+                                    mount_path = "/data"
+                                }
+                                liveness_probe {
+                                    tcp_socket {
+                                        port = "redis"
+                                    }
+                                    success_threshold = 1
+                                    failure_threshold = 10
+                                    period_seconds = 12
+                                    timeout_seconds = 5
+                                }
+                                resources {
+                                    requests = crate::map! {
+                                        "memory" = &this.memory_limit
+                                    }
+                                }
+                                security_context = security::stateful_set::container()
+                            }
+                            container = sidecars
+                            volume {
+                                name = "data"
+                                persistent_volume_claim {
+                                    claim_name = &this.volume_claim
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        };
+
+        this
+    }
+}
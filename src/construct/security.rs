@@ -0,0 +1,121 @@
+//! Security contexts shared by all workloads. Pods run as non-root user using the `RuntimeDefault`
+//! seccomp profile, while containers drop all capabilities, cannot escalate privileges and only
+//! write to volumes. Complies with the `restricted` Pod Security Standard.
+//!
+//! Every workload kind uses distinct generated types, so the functions are generated for each of
+//! them (e.g. [`deployment::pod`]).
+
+/// Seccomp profile applied to all pods.
+const SECCOMP_PROFILE: &str = "RuntimeDefault";
+
+macro_rules! security_contexts {
+    (
+        $module:ident, $resource:ident,
+        pod: $pod:ident, $pod_seccomp:ident,
+        container: $container:ident, $capabilities:ident,
+        init_container: $init_container:ident, $init_capabilities:ident $(,)?
+    ) => {
+        #[allow(dead_code)]
+        pub mod $module {
+            use tf_kubernetes::kubernetes::resource::$resource::{
+                $capabilities, $container, $init_capabilities, $init_container, $pod, $pod_seccomp,
+            };
+
+            /// Returns the security context of a pod running as `user` and `group`. Volumes
+            /// supporting ownership management are writable by `group`.
+            pub fn pod(user: i64, group: i64) -> $pod {
+                $pod::builder()
+                    .run_as_user(user.to_string())
+                    .run_as_group(group.to_string())
+                    .fs_group(group.to_string())
+                    .run_as_non_root(true)
+                    .seccomp_profile(
+                        $pod_seccomp::builder()
+                            .r#type(super::SECCOMP_PROFILE)
+                            .build(),
+                    )
+                    .build()
+            }
+
+            /// Returns the security context of a pod running as the user of its images. Only used
+            /// by pods which have to run as root.
+            pub fn root_pod() -> $pod {
+                $pod::builder()
+                    .seccomp_profile(
+                        $pod_seccomp::builder()
+                            .r#type(super::SECCOMP_PROFILE)
+                            .build(),
+                    )
+                    .build()
+            }
+
+            /// Returns the security context of a container with read-only root filesystem.
+            pub fn container() -> $container {
+                $container::builder()
+                    .allow_privilege_escalation(false)
+                    .read_only_root_filesystem(true)
+                    .capabilities($capabilities::builder().drop(["ALL"]).build())
+                    .build()
+            }
+
+            /// Same as [`container`], but allows writing to the root filesystem. Only used by
+            /// images modifying their installation directory on startup.
+            pub fn writable_container() -> $container {
+                $container::builder()
+                    .allow_privilege_escalation(false)
+                    .read_only_root_filesystem(false)
+                    .capabilities($capabilities::builder().drop(["ALL"]).build())
+                    .build()
+            }
+
+            /// Returns the security context of an init container with read-only root filesystem.
+            pub fn init_container() -> $init_container {
+                $init_container::builder()
+                    .allow_privilege_escalation(false)
+                    .read_only_root_filesystem(true)
+                    .capabilities($init_capabilities::builder().drop(["ALL"]).build())
+                    .build()
+            }
+        }
+    };
+}
+
+security_contexts!(
+    deployment, kubernetes_deployment,
+    pod: KubernetesDeploymentSpecTemplateSpecSecurityContext,
+        KubernetesDeploymentSpecTemplateSpecSecurityContextSeccompProfile,
+    container: KubernetesDeploymentSpecTemplateSpecContainerSecurityContext,
+        KubernetesDeploymentSpecTemplateSpecContainerSecurityContextCapabilities,
+    init_container: KubernetesDeploymentSpecTemplateSpecInitContainerSecurityContext,
+        KubernetesDeploymentSpecTemplateSpecInitContainerSecurityContextCapabilities,
+);
+
+security_contexts!(
+    stateful_set, kubernetes_stateful_set,
+    pod: KubernetesStatefulSetSpecTemplateSpecSecurityContext,
+        KubernetesStatefulSetSpecTemplateSpecSecurityContextSeccompProfile,
+    container: KubernetesStatefulSetSpecTemplateSpecContainerSecurityContext,
+        KubernetesStatefulSetSpecTemplateSpecContainerSecurityContextCapabilities,
+    init_container: KubernetesStatefulSetSpecTemplateSpecInitContainerSecurityContext,
+        KubernetesStatefulSetSpecTemplateSpecInitContainerSecurityContextCapabilities,
+);
+
+security_contexts!(
+    daemonset, kubernetes_daemonset,
+    pod: KubernetesDaemonsetSpecTemplateSpecSecurityContext,
+        KubernetesDaemonsetSpecTemplateSpecSecurityContextSeccompProfile,
+    container: KubernetesDaemonsetSpecTemplateSpecContainerSecurityContext,
+        KubernetesDaemonsetSpecTemplateSpecContainerSecurityContextCapabilities,
+    init_container: KubernetesDaemonsetSpecTemplateSpecInitContainerSecurityContext,
+        KubernetesDaemonsetSpecTemplateSpecInitContainerSecurityContextCapabilities,
+);
+
+security_contexts!(
+    job, kubernetes_job,
+    pod: KubernetesJobSpecTemplateSpecSecurityContext,
+        KubernetesJobSpecTemplateSpecSecurityContextSeccompProfile,
+    container: KubernetesJobSpecTemplateSpecContainerSecurityContext,
+        KubernetesJobSpecTemplateSpecContainerSecurityContextCapabilities,
+    init_container: KubernetesJobSpecTemplateSpecInitContainerSecurityContext,
+        KubernetesJobSpecTemplateSpecInitContainerSecurityContextCapabilities,
+);
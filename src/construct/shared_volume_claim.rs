@@ -0,0 +1,75 @@
+use std::cell::{Ref, RefCell};
+use std::rc::Rc;
+
+use tf_bindgen::codegen::{resource, Construct};
+use tf_bindgen::value::{IntoValue, Value};
+use tf_bindgen::Scope;
+
+use tf_kubernetes::kubernetes::resource::kubernetes_persistent_volume_claim;
+
+/// Volume claim mountable by pods on multiple nodes (`ReadWriteMany`). The volume is provisioned
+/// dynamically using `storage_class` (e.g. an NFS or CephFS provisioner).
+#[derive(Construct)]
+#[construct(builder)]
+#[allow(dead_code)]
+pub struct SharedVolumeClaim {
+    #[construct(id)]
+    name: String,
+    #[construct(scope)]
+    scope: Rc<dyn Scope>,
+    #[construct(setter(into_value))]
+    namespace: Value<String>,
+    #[construct(setter(into_value))]
+    storage: Value<String>,
+    #[construct(setter(into_value))]
+    storage_class: Value<String>,
+    #[construct(skip)]
+    claim_ref: RefCell<Option<Value<String>>>,
+}
+
+impl SharedVolumeClaim {
+    /// Returns a Terraform value reference to the name of the generated volume claim.
+    pub fn claim(&self) -> Ref<'_, Option<Value<String>>> {
+        self.claim_ref.borrow()
+    }
+}
+
+impl SharedVolumeClaimBuilder {
+    pub fn build(&mut self) -> Rc<SharedVolumeClaim> {
+        let this = Rc::new(SharedVolumeClaim {
+            name: self.name.clone(),
+            scope: self.scope.clone(),
+            namespace: self.namespace.clone().expect("missing field 'namespace'"),
+            storage: self.storage.clone().expect("missing field 'storage'"),
+            storage_class: self
+                .storage_class
+                .clone()
+                .expect("missing field 'storage_class'"),
+            claim_ref: RefCell::new(None),
+        });
+        let name = &this.name;
+
+        let claim = resource! {
+            &this, resource "kubernetes_persistent_volume_claim" "claim" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-pvc")
+                }
+                spec {
+                    storage_class_name = &this.storage_class
+                    access_modes = [
+                        "ReadWriteMany"
+                    ]
+                    resources {
+                        requests = crate::map!{
+                            "storage" = &this.storage
+                        }
+                    }
+                }
+            }
+        };
+        this.claim_ref
+            .replace(Some((&claim.metadata[0].name).into_value()));
+        this
+    }
+}
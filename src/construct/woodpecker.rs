@@ -0,0 +1,485 @@
+use std::rc::Rc;
+
+use tf_bindgen::codegen::{resource, Construct};
+use tf_bindgen::value::Value;
+use tf_bindgen::Scope;
+use tf_kubernetes::kubernetes::resource::{
+    kubernetes_deployment, kubernetes_secret, kubernetes_service,
+};
+use url::Url;
+
+use super::ci::CiServer;
+use super::dind;
+use super::ingress::IngressServiceConfig;
+use super::monitoring::{MetricsToken, Monitoring, PodMonitor};
+use super::network_policy::{NetworkEndpoint, NetworkPeer, NetworkPolicy};
+use super::security;
+
+const OAUTH_SCRIPT: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/script/gitea/oauth-app.py"
+));
+const GITEA_API_SCRIPT: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/script/gitea/gitea_api.py"
+));
+
+const HTTP_PORT: i64 = 8000;
+const GRPC_PORT: i64 = 9000;
+
+/// [Woodpecker](https://woodpecker-ci.org/) CI server using Gitea to authenticate users and to
+/// receive repository events. Pipelines are executed by agents using a Docker-in-Docker sidecar,
+/// which requires a namespace allowing privileged pods (`agent_namespace`).
+#[derive(Construct)]
+#[construct(builder)]
+#[allow(dead_code)]
+pub struct Woodpecker {
+    #[construct(id)]
+    name: String,
+    #[construct(scope)]
+    scope: Rc<dyn Scope>,
+    #[construct(setter(into_value))]
+    namespace: Value<String>,
+    /// Namespace of the agents. Defaults to `namespace`.
+    #[construct(setter(into_value))]
+    agent_namespace: Value<String>,
+    #[construct(setter(into))]
+    domain: String,
+    #[construct(setter(into))]
+    path: String,
+    /// External URL of Gitea used to sign in users.
+    #[construct(setter(into))]
+    gitea_url: String,
+    #[construct(setter(into_value))]
+    gitea_api_url: Value<String>,
+    /// Name of the secret storing the credentials of the Gitea root user. Used to register the
+    /// OAuth2 application of this server.
+    #[construct(setter(into_value))]
+    gitea_credentials: Value<String>,
+    /// Gitea user granted administrator permissions.
+    #[construct(setter(into_value))]
+    admin: Value<String>,
+    /// Shared secret used to authenticate agents.
+    #[construct(setter(into_value))]
+    agent_secret: Value<String>,
+    /// Number of agent pods.
+    #[construct(setter(into))]
+    agents: i64,
+    #[construct(setter(into_value))]
+    db_host: Value<String>,
+    #[construct(setter(into_value))]
+    db_name: Value<String>,
+    #[construct(setter(into_value))]
+    db_user: Value<String>,
+    #[construct(setter(into_value))]
+    db_password: Value<String>,
+    /// Exposes server metrics at `{path}/metrics` scraped by Prometheus using `metrics_token`.
+    #[construct(setter(into))]
+    monitoring: Option<Monitoring>,
+    #[construct(setter(into))]
+    metrics_token: Option<String>,
+}
+
+impl CiServer for Woodpecker {
+    fn kind(&self) -> &'static str {
+        "Woodpecker"
+    }
+
+    fn url(&self) -> String {
+        format!("https://{}{}", self.domain, self.path)
+    }
+
+    fn ingress(&self) -> IngressServiceConfig {
+        IngressServiceConfig {
+            rewrite: false,
+            path: self.path.clone(),
+            service_name: format!("{}-server", self.name),
+            service_port: HTTP_PORT,
+            pod_labels: self.endpoint().pod_labels,
+            routed_paths: Vec::new(),
+            blocked_paths: Vec::new(),
+        }
+    }
+
+    fn endpoint(&self) -> NetworkEndpoint {
+        NetworkEndpoint::app(
+            format!("woodpecker-{}-server", self.name),
+            [HTTP_PORT, GRPC_PORT],
+        )
+    }
+
+    fn peer(&self) -> NetworkPeer {
+        NetworkPeer::app(format!("woodpecker-{}-server", self.name))
+    }
+
+    fn gitea_redirect_uri(&self) -> Option<String> {
+        Some(format!("{}/authorize", self.url()))
+    }
+}
+
+impl WoodpeckerBuilder {
+    pub fn build(&mut self) -> Rc<Woodpecker> {
+        let this = Rc::new(Woodpecker {
+            name: self.name.clone(),
+            scope: self.scope.clone(),
+            namespace: self.namespace.clone().expect("missing field 'namespace'"),
+            agent_namespace: self
+                .agent_namespace
+                .clone()
+                .or_else(|| self.namespace.clone())
+                .expect("missing field 'namespace'"),
+            domain: self.domain.clone().expect("missing field 'domain'"),
+            path: self.path.clone().unwrap_or("/woodpecker".into()),
+            gitea_url: self.gitea_url.clone().expect("missing field 'gitea_url'"),
+            gitea_api_url: self
+                .gitea_api_url
+                .clone()
+                .expect("missing field 'gitea_api_url'"),
+            gitea_credentials: self
+                .gitea_credentials
+                .clone()
+                .expect("missing field 'gitea_credentials'"),
+            admin: self.admin.clone().expect("missing field 'admin'"),
+            agent_secret: self
+                .agent_secret
+                .clone()
+                .expect("missing field 'agent_secret'"),
+            agents: self.agents.unwrap_or(1),
+            db_host: self.db_host.clone().expect("missing field 'db_host'"),
+            db_name: self.db_name.clone().expect("missing field 'db_name'"),
+            db_user: self.db_user.clone().expect("missing field 'db_user'"),
+            db_password: self
+                .db_password
+                .clone()
+                .expect("missing field 'db_password'"),
+            monitoring: self.monitoring.clone().flatten(),
+            metrics_token: self.metrics_token.clone().flatten(),
+        });
+        assert!(
+            this.monitoring.is_none() || this.metrics_token.is_some(),
+            "monitoring requires a metrics token"
+        );
+
+        let name = &this.name;
+        let server_labels = crate::map! {
+            "app" = format!("woodpecker-{name}-server")
+        };
+        let agent_labels = crate::map! {
+            "app" = format!("woodpecker-{name}-agent")
+        };
+        NetworkPolicy::create(&this, format!("{name}-agents"))
+            .namespace(&this.namespace)
+            .endpoint(NetworkEndpoint::app(
+                format!("woodpecker-{name}-server"),
+                [GRPC_PORT],
+            ))
+            .from(vec![NetworkPeer::app(format!("woodpecker-{name}-agent"))
+                .in_namespace(this.agent_namespace.get().as_str())])
+            .build();
+
+        // Credentials are percent-encoded, since they may contain reserved characters.
+        let mut datasource = Url::parse(&format!(
+            "postgres://{}/{}?sslmode=disable",
+            this.db_host.get(),
+            this.db_name.get()
+        ))
+        .expect("valid postgres url");
+        datasource
+            .set_username(&this.db_user.get())
+            .expect("postgres url with host");
+        datasource
+            .set_password(Some(&this.db_password.get()))
+            .expect("postgres url with host");
+        let datasource = datasource.to_string();
+        let mut env_data = crate::map! {
+            "WOODPECKER_AGENT_SECRET" = &this.agent_secret,
+            "WOODPECKER_DATABASE_DATASOURCE" = &datasource
+        };
+        if let Some(token) = &this.metrics_token {
+            env_data.extend(crate::map! {
+                "WOODPECKER_PROMETHEUS_AUTH_TOKEN" = token
+            });
+        }
+        let checksum = crate::helper::checksum((
+            OAUTH_SCRIPT,
+            GITEA_API_SCRIPT,
+            &datasource,
+            this.agent_secret.get().as_str(),
+            &this.metrics_token,
+        ));
+        let env = resource! {
+            &this, resource "kubernetes_secret" "woodpecker-env" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-env")
+                }
+                data = env_data
+            }
+        };
+        if let (Some(monitoring), Some(token)) = (&this.monitoring, &this.metrics_token) {
+            PodMonitor::create(&this, format!("{name}-metrics"))
+                .namespace(&this.namespace)
+                .monitoring(monitoring.clone())
+                .pod_labels([("app".to_string(), format!("woodpecker-{name}-server"))])
+                .port("http")
+                .path(format!("{}/metrics", this.path))
+                .metrics_port(HTTP_PORT)
+                .token(MetricsToken {
+                    secret_name: format!("{name}-env"),
+                    key: "WOODPECKER_PROMETHEUS_AUTH_TOKEN".to_string(),
+                    value: token.clone(),
+                })
+                .build();
+        }
+        let oauth_config = resource! {
+            &this, resource "kubernetes_secret" "woodpecker-oauth" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-oauth")
+                }
+                data = crate::map! {
+                    "oauth-app.py" = OAUTH_SCRIPT,
+                    "gitea_api.py" = GITEA_API_SCRIPT
+                }
+            }
+        };
+
+        resource! {
+            &this, resource "kubernetes_service" "woodpecker-server" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-server")
+                }
+                spec {
+                    selector = &server_labels
+                    port {
+                        name = "http"
+                        port = HTTP_PORT
+                    }
+                    port {
+                        name = "grpc"
+                        port = GRPC_PORT
+                    }
+                }
+            }
+        };
+
+        resource! {
+            &this, resource "kubernetes_deployment" "woodpecker-server" {
+                metadata {
+                    namespace = &this.namespace
+                    name = format!("{name}-server")
+                }
+                spec {
+                    replicas = "1"
+                    selector {
+                        match_labels = &server_labels
+                    }
+                    template {
+                        metadata {
+                            labels = &server_labels
+                            annotations = crate::map! {
+                                "gitserver/checksum" = &checksum
+                            }
+                        }
+                        spec {
+                            security_context = security::deployment::pod(1000, 1000)
+                            init_container {
+                                name = "oauth-app"
+                                image = "python:3.11-alpine"
+                                command = ["python3", "/config/oauth-app.py"]
+                                env {
+                                    name = "GITEA_API_URL"
+                                    value = &this.gitea_api_url
+                                }
+                                env {
+                                    name = "APP_NAME"
+                                    value = name
+                                }
+                                env {
+                                    name = "REDIRECT_URI"
+                                    value = this.gitea_redirect_uri().unwrap()
+                                }
+                                env {
+                                    name = "OUTPUT_DIR"
+                                    value = "/oauth"
+                                }
+                                env_from {
+                                    secret_ref {
+                                        name = &this.gitea_credentials
+                                    }
+                                }
+                                volume_mount {
+                                    name = "config"
+                                    mount_path = "/config"
+                                }
+                                volume_mount {
+                                    name = "oauth"
+                                    mount_path = "/oauth"
+                                }
+                                security_context = security::deployment::init_container()
+                            }
+                            container {
+                                name = "server"
+                                image = "woodpeckerci/woodpecker-server:v2.1.1"
+                                port {
+                                    name = "http"
+                                    container_port = HTTP_PORT
+                                }
+                                port {
+                                    name = "grpc"
+                                    container_port = GRPC_PORT
+                                }
+                                env {
+                                    name = "WOODPECKER_HOST"
+                                    value = format!("https://{}", this.domain)
+                                }
+                                env {
+                                    name = "WOODPECKER_ROOT_PATH"
+                                    value = &this.path
+                                }
+                                env {
+                                    name = "WOODPECKER_OPEN"
+                                    value = "true"
+                                }
+                                env {
+                                    name = "WOODPECKER_ADMIN"
+                                    value = &this.admin
+                                }
+                                env {
+                                    name = "WOODPECKER_GITEA"
+                                    value = "true"
+                                }
+                                env {
+                                    name = "WOODPECKER_GITEA_URL"
+                                    value = this.gitea_url.trim_end_matches('/')
+                                }
+                                env {
+                                    name = "WOODPECKER_GITEA_CLIENT_FILE"
+                                    value = "/oauth/client_id"
+                                }
+                                env {
+                                    name = "WOODPECKER_GITEA_SECRET_FILE"
+                                    value = "/oauth/client_secret"
+                                }
+                                env {
+                                    name = "WOODPECKER_DATABASE_DRIVER"
+                                    value = "postgres"
+                                }
+                                env_from {
+                                    secret_ref {
+                                        name = &env.metadata[0].name
+                                    }
+                                }
+                                volume_mount {
+                                    name = "oauth"
+                                    mount_path = "/oauth"
+                                }
+                                readiness_probe {
+                                    tcp_socket {
+                                        port = "http"
+                                    }
+                                }
+                                liveness_probe {
+                                    tcp_socket {
+                                        port = "http"
+                                    }
+                                    success_threshold = 1
+                                    failure_threshold = 10
+                                    period_seconds = 12
+                                    timeout_seconds = 5
+                                }
+                                security_context = security::deployment::container()
+                            }
+                            volume {
+                                name = "config"
+                                secret {
+                                    secret_name = &oauth_config.metadata[0].name
+                                }
+                            }
+                            volume {
+                                name = "oauth"
+                                empty_dir {}
+                            }
+                        }
+                    }
+                }
+            }
+        };
+
+        let agent_env = resource! {
+            &this, resource "kubernetes_secret" "woodpecker-agent" {
+                metadata {
+                    namespace = &this.agent_namespace
+                    name = format!("{name}-agent")
+                }
+                data = crate::map! {
+                    "WOODPECKER_AGENT_SECRET" = &this.agent_secret
+                }
+            }
+        };
+        resource! {
+            &this, resource "kubernetes_deployment" "woodpecker-agent" {
+                metadata {
+                    namespace = &this.agent_namespace
+                    name = format!("{name}-agent")
+                }
+                spec {
+                    replicas = this.agents.to_string()
+                    selector {
+                        match_labels = &agent_labels
+                    }
+                    template {
+                        metadata {
+                            labels = &agent_labels
+                            annotations = crate::map! {
+                                "gitserver/checksum" = &checksum
+                            }
+                        }
+                        spec {
+                            security_context = security::deployment::pod(1000, 1000)
+                            container {
+                                name = "agent"
+                                image = "woodpeckerci/woodpecker-agent:v2.1.1"
+                                env {
+                                    name = "WOODPECKER_SERVER"
+                                    value = format!(
+                                        "{name}-server.{}.svc:{GRPC_PORT}",
+                                        this.namespace.get()
+                                    )
+                                }
+                                env {
+                                    name = "WOODPECKER_BACKEND"
+                                    value = "docker"
+                                }
+                                env {
+                                    name = "WOODPECKER_AGENT_SECRET"
+                                    value_from {
+                                        secret_key_ref {
+                                            name = &agent_env.metadata[0].name
+                                            key = "WOODPECKER_AGENT_SECRET"
+                                        }
+                                    }
+                                }
+                                env = dind::deployment::docker_env()
+                                // Stores the ID assigned by the server.
+                                volume_mount {
+                                    name = "config"
+                                    mount_path = "/etc/woodpecker"
+                                }
+                                security_context = security::deployment::container()
+                            }
+                            container = vec![dind::deployment::sidecar()]
+                            volume {
+                                name = "config"
+                                empty_dir {}
+                            }
+                        }
+                    }
+                }
+            }
+        };
+
+        this
+    }
+}
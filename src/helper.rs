@@ -1,3 +1,7 @@
+use sha2::{Digest, Sha256};
+use tf_bindgen::json;
+use tf_bindgen::serde::Serialize;
+
 #[macro_export]
 macro_rules! map {
     ($( $key:literal = $value:expr ),*$(,)?) => {{
@@ -10,3 +14,27 @@ macro_rules! map {
 		map
     }};
 }
+
+/// Returns the hex encoded SHA-256 checksum of the JSON serialization of `value`. Used to annotate
+/// pod templates to force a restart if configuration referenced by these pods changed. Unlike the
+/// data fed to `Hash` implementations, the serialization does not change between Rust releases,
+/// which would restart all pods.
+pub fn checksum(value: impl Serialize) -> String {
+    let bytes = json::to_vec(&value).expect("serializable checksum value");
+    let digest = Sha256::digest(bytes);
+    digest.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Returns `value` as YAML document. JSON is a subset of YAML, so components reading YAML
+/// configuration files accept the pretty-printed JSON as is.
+pub fn yaml(value: &json::Value) -> String {
+    json::to_string_pretty(value).expect("serializable json value")
+}
+
+/// Returns a random hex encoded secret of `bytes` bytes read from the system's random number
+/// generator.
+pub fn random_secret(bytes: usize) -> Result<String, getrandom::Error> {
+    let mut secret = vec![0u8; bytes];
+    getrandom::getrandom(&mut secret)?;
+    Ok(secret.iter().map(|byte| format!("{byte:02x}")).collect())
+}
@@ -0,0 +1,111 @@
+use std::io::Write;
+use std::process::Stdio;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context, Result};
+use tf_bindgen::json::{self, Value};
+use tf_bindgen::Stack;
+
+use crate::cli::ImportArgs;
+use crate::kubectl::{Kubectl, ONE_OFF_LABEL};
+use crate::terraform::Outputs;
+
+/// Imports the repositories declared in `[[gitea.mirrors]]` immediately. Starts a one-off copy of
+/// the bootstrap job restricted to mirrors, streams its logs and returns its exit code.
+pub fn run(stack: &Stack, args: &ImportArgs) -> Result<i32> {
+    let outputs = Outputs::read(stack)?;
+    let output = |name: &str| {
+        outputs
+            .get(name)
+            .and_then(Value::as_str)
+            .with_context(|| format!("missing output '{name}' (did you run apply?)"))
+    };
+    let kubectl = Kubectl::new(output("namespace")?);
+    let bootstrap = output("gitea_bootstrap_job")?;
+
+    let job = import_job(&kubectl, bootstrap, args)?;
+    let name = job["metadata"]["name"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let mut create = kubectl
+        .command()
+        .args(["create", "-f", "-"])
+        .stdin(Stdio::piped())
+        .spawn()
+        .context("failed to run kubectl")?;
+    create
+        .stdin
+        .take()
+        .context("failed to open kubectl stdin")?
+        .write_all(json::to_string(&job)?.as_bytes())?;
+    if !create.wait()?.success() {
+        bail!("failed to create job {name}")
+    }
+
+    kubectl
+        .command()
+        .args(["logs", "--follow", "--pod-running-timeout=5m"])
+        .arg(format!("job/{name}"))
+        .status()
+        .context("failed to run kubectl")?;
+    let complete = kubectl
+        .command()
+        .args(["wait", "--for=condition=complete", "--timeout=30s"])
+        .arg(format!("job/{name}"))
+        .stdout(Stdio::null())
+        .status()
+        .context("failed to run kubectl")?;
+    match complete.success() {
+        true => Ok(0),
+        false => {
+            eprintln!("import failed, see `kubectl logs job/{name}`");
+            Ok(1)
+        }
+    }
+}
+
+/// Returns a job using the pod template of the bootstrap job `bootstrap` to reconcile mirrors.
+fn import_job(kubectl: &Kubectl, bootstrap: &str, args: &ImportArgs) -> Result<Value> {
+    let jobs = kubectl.get_with(
+        "jobs",
+        &["--field-selector", &format!("metadata.name={bootstrap}")],
+    )?;
+    let Some(bootstrap_job) = jobs.into_iter().next() else {
+        bail!("bootstrap job {bootstrap} not found")
+    };
+    let mut template = bootstrap_job["spec"]["template"].clone();
+    // Labels are generated by the job controller and must not be copied, except for the `app`
+    // label selected by the network policies of Gitea.
+    template["metadata"]["labels"]
+        .as_object_mut()
+        .context("invalid bootstrap job")?
+        .retain(|label, _| label == "app");
+    let mut env = vec![
+        json::json!({ "name": "RECONCILE", "value": "mirrors" }),
+        json::json!({ "name": "SYNC_MIRRORS", "value": "1" }),
+    ];
+    if let Some(only) = &args.only {
+        env.push(json::json!({ "name": "ONLY", "value": only }));
+    }
+    template["spec"]["restartPolicy"] = "Never".into();
+    template["spec"]["containers"][0]["env"]
+        .as_array_mut()
+        .context("invalid bootstrap job")?
+        .extend(env);
+
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    Ok(json::json!({
+        "apiVersion": "batch/v1",
+        "kind": "Job",
+        "metadata": {
+            "name": format!("{bootstrap}-import-{timestamp}"),
+            "labels": { ONE_OFF_LABEL: "true" }
+        },
+        "spec": {
+            "backoffLimit": 0,
+            "ttlSecondsAfterFinished": 3600,
+            "template": template
+        }
+    }))
+}
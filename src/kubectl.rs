@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use tf_bindgen::json;
+
+/// Path to the kubeconfig used by the Kubernetes provider and `kubectl`.
+pub const KUBE_CONFIG: &str = "~/.kube/config";
+
+/// Label of jobs started outside of Terraform (e.g. by `gitserver import`). These are not waited
+/// for by [`Kubectl::wait_for_rollout`].
+pub const ONE_OFF_LABEL: &str = "gitserver/one-off";
+
+/// Thin wrapper around the `kubectl` binary scoped to a single namespace.
+pub struct Kubectl {
+    namespace: String,
+}
+
+impl Kubectl {
+    pub fn new(namespace: impl Into<String>) -> Self {
+        Self {
+            namespace: namespace.into(),
+        }
+    }
+
+    /// Returns a wrapper for every namespace of an applied stack using its `outputs` (see
+    /// [`Outputs::read`](crate::terraform::Outputs::read)).
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the stack was not applied yet.
+    pub fn namespaces(outputs: &HashMap<String, json::Value>) -> Result<Vec<Self>> {
+        let namespace = outputs
+            .get("namespace")
+            .and_then(json::Value::as_str)
+            .context("missing output 'namespace' (did you run apply?)")?;
+        let privileged = outputs
+            .get("privileged_namespace")
+            .and_then(json::Value::as_str);
+        Ok([Some(namespace), privileged]
+            .into_iter()
+            .flatten()
+            .map(Self::new)
+            .collect())
+    }
+
+    /// Returns a prepared `kubectl` command using [`KUBE_CONFIG`] and the configured namespace.
+    pub fn command(&self) -> Command {
+        let mut command = Command::new("kubectl");
+        command
+            .arg(format!("--kubeconfig={}", expand_home(KUBE_CONFIG)))
+            .arg(format!("--namespace={}", self.namespace));
+        command
+    }
+
+    /// Runs `kubectl get <kind> -o json` and returns the list of returned items.
+    pub fn get(&self, kind: &str) -> Result<Vec<json::Value>> {
+        self.get_with(kind, &[])
+    }
+
+    /// Same as [`Kubectl::get`] but passes `args` as additional arguments (e.g. selectors).
+    pub fn get_with(&self, kind: &str, args: &[&str]) -> Result<Vec<json::Value>> {
+        let output = self
+            .command()
+            .args(["get", kind, "-o", "json"])
+            .args(args)
+            .output()
+            .context("failed to run kubectl")?;
+        if !output.status.success() {
+            bail!(
+                "kubectl get {kind} failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+        }
+        let list: json::Value =
+            json::from_slice(&output.stdout).context("failed to parse kubectl output")?;
+        match list.get("items") {
+            Some(json::Value::Array(items)) => Ok(items.clone()),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Waits until all stateful sets, deployments and daemon sets in the namespace finished their
+    /// rollout and all jobs completed. One-off jobs (see [`ONE_OFF_LABEL`]) are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a rollout or job failed or did not finish within `timeout`.
+    pub fn wait_for_rollout(&self, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        for kind in ["statefulset", "deployment", "daemonset"] {
+            for workload in self.get(kind)? {
+                let name = workload["metadata"]["name"].as_str().unwrap_or_default();
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    bail!("timed out waiting for {kind}/{name}")
+                }
+                println!("waiting for {kind}/{name} ...");
+                let status = self
+                    .command()
+                    .args(["rollout", "status", &format!("{kind}/{name}")])
+                    .arg(format!("--timeout={}s", remaining.as_secs().max(1)))
+                    .status()
+                    .context("failed to run kubectl")?;
+                if !status.success() {
+                    bail!("{kind}/{name} did not become ready")
+                }
+            }
+        }
+        let selector = format!("!{ONE_OFF_LABEL}");
+        for job in self.get_with("jobs", &["--selector", &selector])? {
+            let name = job["metadata"]["name"].as_str().unwrap_or_default();
+            self.wait_for_job(name, deadline.saturating_duration_since(Instant::now()))?;
+        }
+        Ok(())
+    }
+
+    /// Waits until the job `name` completed.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the job does not exist, failed or did not complete within `timeout`.
+    pub fn wait_for_job(&self, name: &str, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        let selector = format!("metadata.name={name}");
+        println!("waiting for job/{name} ...");
+        loop {
+            let jobs = self.get_with("jobs", &["--field-selector", &selector])?;
+            let job = jobs
+                .first()
+                .with_context(|| format!("job/{name} not found"))?;
+            let condition = |kind: &str| {
+                job["status"]["conditions"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .any(|condition| condition["type"] == kind && condition["status"] == "True")
+            };
+            if condition("Complete") {
+                return Ok(());
+            }
+            if condition("Failed") {
+                bail!("job/{name} failed, see `cargo run -- logs {name}`")
+            }
+            if Instant::now() > deadline {
+                bail!("timed out waiting for job/{name}")
+            }
+            std::thread::sleep(JOB_POLL_INTERVAL);
+        }
+    }
+}
+
+/// Interval between two checks of [`Kubectl::wait_for_job`].
+const JOB_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Replaces a leading `~` with the content of `$HOME`.
+pub fn expand_home(path: &str) -> String {
+    match (path.strip_prefix('~'), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{home}{rest}"),
+        _ => path.to_string(),
+    }
+}
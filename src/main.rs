@@ -1,42 +1,151 @@
+use std::collections::HashMap;
 use std::rc::Rc;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use anyhow::{bail, Context};
 use clap::Parser;
-use cli::{Cli, Command};
+use cli::{ApplyArgs, Cli, Command, StateCommand};
+use construct::alertmanager::Alertmanager;
+use construct::cache::Cache;
+use construct::ci::CiServer;
+use construct::dex::{Dex, DexClient};
+use construct::elasticsearch::Elasticsearch;
 use construct::gitea::Gitea;
+use construct::gitea_auth::{AuthSource, OidcSource};
+use construct::gitea_runner::RunnerExecutor;
+use construct::indexer::Indexer;
 use construct::ingress::Ingress;
-use construct::jenkins::Jenkins;
+use construct::jenkins::{Jenkins, JenkinsSso};
+use construct::mail::{MailConfig, MailTls};
+use construct::mailpit::Mailpit;
 use construct::memcached::Memcached;
+use construct::minio::Minio;
+use construct::monitoring::{Monitoring, ScrapeJobs};
+use construct::network_policy::{NetworkClients, NetworkPeer, NetworkPolicy};
+use construct::object_storage::ObjectStorage;
 use tf_bindgen::{cli::Terraform, Stack};
 use tf_kubernetes::kubernetes::resource::{kubernetes_namespace, kubernetes_storage_class};
 use tf_kubernetes::kubernetes::Kubernetes;
+use url::Url;
 
 mod cli;
+mod component;
 mod config;
 mod construct;
 mod helper;
+mod import;
+mod kubectl;
+mod process;
+mod status;
+mod terraform;
 
-use config::Config;
+use config::{
+    CacheKind, CiKind, Config, IndexerKind, LogSinkKind, Mail, NetworkAllow, Packages, Sso,
+    SyslogProtocol, SSO_SOURCE,
+};
 use construct::local_dir_volume::LocalDirVolume;
-use construct::postgres::Postgres;
+use construct::log_collector::{LogCollector, LogSink};
+use construct::postgres::{Postgres, PostgresPooler, PostgresReplication};
+use construct::prometheus::Prometheus;
+use construct::redis::Redis;
+use construct::shared_volume_claim::SharedVolumeClaim;
+use kubectl::Kubectl;
+use status::Status;
+use terraform::Outputs;
 
-pub fn init(config: Config) -> Rc<Stack> {
+/// Namespace of the workloads requiring privileged pods (e.g. Docker-in-Docker).
+const PRIVILEGED_NAMESPACE: &str = "gitserver-privileged";
+
+pub fn init(config: &Config) -> anyhow::Result<(Rc<Stack>, Outputs)> {
     let stack = Stack::new("gitserver");
+    let mut outputs = Outputs::default();
+    // Generated secrets are stored as outputs and reused on subsequent runs.
+    let previous_outputs = Outputs::read_existing(&stack)?;
+    let generated_secret = |name: &str| -> anyhow::Result<String> {
+        match previous_outputs.get(name).and_then(|value| value.as_str()) {
+            Some(secret) => Ok(secret.to_string()),
+            None => helper::random_secret(24).context("failed to generate secret"),
+        }
+    };
 
     Kubernetes::create(&stack)
-        .config_path("~/.kube/config")
+        .config_path(kubectl::KUBE_CONFIG)
         .build();
 
+    // Pods are checked against the `restricted` Pod Security Standard. Docker-in-Docker and the
+    // log collector require privileged pods, so they run in a separate namespace.
+    let pod_security = |level: &str| -> HashMap<String, String> {
+        ["enforce", "warn", "audit"]
+            .into_iter()
+            .map(|mode| {
+                (
+                    format!("pod-security.kubernetes.io/{mode}"),
+                    level.to_string(),
+                )
+            })
+            .collect()
+    };
     let namespace = tf_bindgen::codegen::resource! {
         &stack, resource "kubernetes_namespace" "gitserver" {
             metadata {
                 name = "gitserver"
+                labels = &pod_security("restricted")
             }
         }
     };
     let namespace = &namespace.metadata[0].name;
+    outputs.add("namespace", namespace);
+    let privileged_namespace = tf_bindgen::codegen::resource! {
+        &stack, resource "kubernetes_namespace" "gitserver-privileged" {
+            metadata {
+                name = PRIVILEGED_NAMESPACE
+                labels = &pod_security("privileged")
+            }
+        }
+    };
+    let privileged_namespace = &privileged_namespace.metadata[0].name;
+    outputs.add("privileged_namespace", privileged_namespace);
+
+    // Pods only accept connections allowed by a network policy. Components register their
+    // endpoints and clients in `network`.
+    NetworkPolicy::create(&stack, "default-deny")
+        .namespace(namespace)
+        .build();
+    NetworkPolicy::create(&stack, "privileged-default-deny")
+        .namespace(privileged_namespace)
+        .build();
+    let mut network = NetworkClients::default();
+
+    // The bundled Prometheus scrapes the jobs registered by all pod monitors, so it is created
+    // after all other components.
+    let scrape_jobs = config
+        .monitoring
+        .as_ref()
+        .filter(|monitoring| monitoring.bundled)
+        .map(|_| ScrapeJobs::default());
+    let monitoring = config.monitoring.as_ref().map(|monitoring| Monitoring {
+        prometheus: match monitoring.bundled {
+            // Pods of the bundled Prometheus created below. Also scrapes the privileged namespace.
+            true => NetworkPeer::app("prometheus-prometheus").in_namespace("gitserver"),
+            false => NetworkPeer::Namespace {
+                name: monitoring.prometheus_namespace.clone(),
+                labels: HashMap::new(),
+            },
+        },
+        labels: monitoring.labels.clone(),
+        scrape_jobs: scrape_jobs.clone(),
+    });
+    // Tokens protecting metrics served on the public port.
+    let (gitea_metrics_token, woodpecker_metrics_token) = match &monitoring {
+        Some(_) => {
+            let gitea_token = generated_secret("gitea_metrics_token")?;
+            let woodpecker_token = generated_secret("woodpecker_metrics_token")?;
+            outputs.add_sensitive("gitea_metrics_token", &gitea_token);
+            outputs.add_sensitive("woodpecker_metrics_token", &woodpecker_token);
+            (Some(gitea_token), Some(woodpecker_token))
+        }
+        None => (None, None),
+    };
 
     let local_storage_class = tf_bindgen::codegen::resource! {
         &stack, resource "kubernetes_storage_class" "local_storage" {
@@ -48,97 +157,720 @@ pub fn init(config: Config) -> Rc<Stack> {
         }
     };
 
-    let pgdata_volume = LocalDirVolume::create(&stack, "gitserver-pgdata")
-        .storage("10Gi")
-        .storage_class(&local_storage_class.metadata[0].name)
-        .mount_path("/mnt/gitea-pgdata")
-        .node(&config.server.node)
-        .build();
-    let pgdata = pgdata_volume.claim("pgdata").namespace(namespace).build();
+    // Multiple replicas require a volume shared between nodes. Switching the volume of an existing
+    // deployment would start with empty repositories, so it is refused.
+    let gitea_storage = match config.ha {
+        Some(_) => "shared",
+        None => "local",
+    };
+    check_storage(&previous_outputs, "gitea_storage", "local", gitea_storage).context(
+        "ha: can not move the repositories of an existing deployment, back up the data using \
+        `gitea dump` and restore it into a new deployment instead",
+    )?;
+    outputs.add("gitea_storage", gitea_storage);
+    let giteadata = match &config.ha {
+        Some(ha) => SharedVolumeClaim::create(&stack, "giteadata")
+            .namespace(namespace)
+            .storage(&ha.size)
+            .storage_class(&ha.storage_class)
+            .build()
+            .claim()
+            .clone()
+            .unwrap(),
+        None => {
+            let giteadata_volume = LocalDirVolume::create(&stack, "gitserver-giteadata")
+                .namespace(privileged_namespace)
+                .storage("10Gi")
+                .storage_class(&local_storage_class.metadata[0].name)
+                .mount_path("/mnt/gitea-data")
+                .owner((1000, 1000))
+                .node(&config.server.node)
+                .build();
+            let giteadata = giteadata_volume
+                .claim("giteadata")
+                .namespace(namespace)
+                .build();
+            giteadata.claim().clone().unwrap()
+        }
+    };
 
-    let giteadata_volume = LocalDirVolume::create(&stack, "gitserver-giteadata")
-        .storage("10Gi")
-        .storage_class(&local_storage_class.metadata[0].name)
-        .mount_path("/mnt/gitea-data")
-        .node(&config.server.node)
-        .build();
-    let giteadata = giteadata_volume
-        .claim("giteadata")
-        .namespace(namespace)
-        .build();
+    let mut ingress_services = Vec::new();
+    let mail = config.mail.as_ref().map(|mail| match mail.catch_all {
+        true => {
+            let mailpit = Mailpit::create(&stack, "mailpit")
+                .namespace(namespace)
+                .path("/mail")
+                .build();
+            network.endpoint("mailpit", mailpit.endpoint());
+            ingress_services.push(mailpit.ingress());
+            mailpit.mail_config(&mail.from)
+        }
+        false => mail_config(mail),
+    });
 
-    Memcached::create(&stack, "giteacache")
-        .namespace(namespace)
-        .memory_limit("256Mi")
-        .build();
-    Postgres::create(&stack, "giteadb")
+    let dex = config.sso.as_ref().map(|sso| {
+        let dexdata_volume = LocalDirVolume::create(&stack, "gitserver-dexdata")
+            .namespace(privileged_namespace)
+            .storage("1Gi")
+            .storage_class(&local_storage_class.metadata[0].name)
+            .mount_path("/mnt/dex-pgdata")
+            .owner((70, 70))
+            .node(&config.server.node)
+            .build();
+        let dexdata = dexdata_volume.claim("dexdata").namespace(namespace).build();
+        let dexdb = Postgres::create(&stack, "dexdb")
+            .namespace(namespace)
+            .volume_claim(dexdata.claim().clone().unwrap())
+            .db_name("dex")
+            .user("dex")
+            .password("dex")
+            .monitoring(monitoring.clone())
+            .build();
+        let dex = Dex::create(&stack, "dex")
+            .namespace(namespace)
+            .domain(&config.server.domain)
+            .path("/dex")
+            .db_host("postgres-dexdb.gitserver")
+            .db_name("dex")
+            .db_user("dex")
+            .db_password("dex")
+            .clients(sso_clients(&config.server.domain, sso, config.ci.kind))
+            .connectors(sso.connectors.clone())
+            .static_passwords(sso.static_passwords())
+            .monitoring(monitoring.clone())
+            .build();
+        network.endpoint("dexdb", dexdb.endpoint());
+        network.endpoint("dex", dex.endpoint());
+        network.allow("dexdb", dex.peer());
+        ingress_services.push(dex.ingress());
+        dex
+    });
+
+    let mut auth_sources = config.gitea.auth.sources();
+    let mut jenkins_sso = None;
+    if let (Some(dex), Some(sso)) = (&dex, &config.sso) {
+        auth_sources.push(AuthSource::OpenIdConnect(OidcSource {
+            name: SSO_SOURCE.to_string(),
+            discovery_url: dex.discovery_url(),
+            client_id: "gitea".to_string(),
+            client_secret: sso.gitea_client_secret.clone(),
+            scopes: ["openid", "email", "profile", "groups"]
+                .map(String::from)
+                .to_vec(),
+            group_claim: Some("groups".to_string()),
+            admin_group: sso.admin_group.clone(),
+            group_team_map: sso.group_team_map(),
+        }));
+        jenkins_sso = sso.jenkins_client_secret.as_ref().map(|secret| JenkinsSso {
+            discovery_url: dex.discovery_url(),
+            client_id: "jenkins".to_string(),
+            client_secret: secret.clone(),
+            admin_groups: sso.admin_group.iter().cloned().collect(),
+            developer_groups: sso.developer_groups.clone(),
+        });
+    }
+
+    let object_storage = config.storage.as_ref().map(|storage| {
+        let bucket = match storage.minio {
+            true => {
+                let miniodata_volume = LocalDirVolume::create(&stack, "gitserver-miniodata")
+                    .namespace(privileged_namespace)
+                    .storage("50Gi")
+                    .storage_class(&local_storage_class.metadata[0].name)
+                    .mount_path("/mnt/gitea-minio")
+                    .owner((1000, 1000))
+                    .node(&config.server.node)
+                    .build();
+                let miniodata = miniodata_volume
+                    .claim("miniodata")
+                    .namespace(namespace)
+                    .build();
+                let secret_key = generated_secret("minio_secret_key")?;
+                outputs.add_sensitive("minio_secret_key", &secret_key);
+                let minio = Minio::create(&stack, "minio")
+                    .namespace(namespace)
+                    .volume_claim(miniodata.claim().clone().unwrap())
+                    .secret_key(secret_key)
+                    .monitoring(monitoring.clone())
+                    .build();
+                network.endpoint("minio", minio.endpoint());
+                minio.storage(&storage.bucket)
+            }
+            false => ObjectStorage {
+                endpoint: storage
+                    .endpoint
+                    .clone()
+                    .expect("validated storage endpoint"),
+                bucket: storage.bucket.clone(),
+                region: storage.region.clone(),
+                access_key: storage.access_key.clone().expect("validated access key"),
+                secret_key: storage.secret_key.clone().expect("validated secret key"),
+                use_ssl: storage.use_ssl,
+            },
+        };
+        let mut sections: HashMap<_, _> = storage
+            .targets
+            .iter()
+            .map(|target| (target.section().to_string(), bucket.clone()))
+            .collect();
+        if let Some(Packages {
+            bucket: Some(name), ..
+        }) = &config.packages
+        {
+            let packages = ObjectStorage {
+                bucket: name.clone(),
+                ..bucket
+            };
+            sections.insert("packages".to_string(), packages);
+        }
+        sections
+    });
+
+    let indexer = config.indexer.as_ref().map(|indexer| {
+        if let Some(url) = &indexer.url {
+            return Indexer::Elasticsearch(url.clone());
+        }
+        let (name, mount_path) = match indexer.kind {
+            IndexerKind::Bleve => ("indexers", "/mnt/gitea-indexers"),
+            IndexerKind::Elasticsearch => ("elasticsearch", "/mnt/gitea-elasticsearch"),
+        };
+        let indexdata_volume = LocalDirVolume::create(&stack, format!("gitserver-{name}"))
+            .namespace(privileged_namespace)
+            .storage(&indexer.size)
+            .storage_class(&local_storage_class.metadata[0].name)
+            .mount_path(mount_path)
+            .owner((1000, 1000))
+            .node(&config.server.node)
+            .build();
+        let indexdata = indexdata_volume.claim(name).namespace(namespace).build();
+        let volume_claim = indexdata.claim().clone().unwrap();
+        match indexer.kind {
+            IndexerKind::Bleve => Indexer::Bleve(volume_claim),
+            IndexerKind::Elasticsearch => {
+                let elasticsearch = Elasticsearch::create(&stack, "giteasearch")
+                    .namespace(namespace)
+                    .volume_claim(volume_claim)
+                    .monitoring(monitoring.clone())
+                    .build();
+                network.endpoint("giteasearch", elasticsearch.endpoint());
+                Indexer::Elasticsearch(elasticsearch.url())
+            }
+        }
+    });
+
+    let cache = match config.cache.kind {
+        CacheKind::Memcached => {
+            let memcached = Memcached::create(&stack, "giteacache")
+                .namespace(namespace)
+                .memory_limit("256Mi")
+                .monitoring(monitoring.clone())
+                .build();
+            network.endpoint("giteacache", memcached.endpoint());
+            memcached.cache()
+        }
+        CacheKind::Redis => {
+            let redisdata_volume = LocalDirVolume::create(&stack, "gitserver-redisdata")
+                .namespace(privileged_namespace)
+                .storage("1Gi")
+                .storage_class(&local_storage_class.metadata[0].name)
+                .mount_path("/mnt/gitea-redis")
+                .owner((999, 1000))
+                .node(&config.server.node)
+                .build();
+            let redisdata = redisdata_volume
+                .claim("redisdata")
+                .namespace(namespace)
+                .build();
+            let password = match &config.cache.password {
+                Some(password) => password.clone(),
+                None => generated_secret("redis_password")?,
+            };
+            outputs.add_sensitive("redis_password", &password);
+            let redis = Redis::create(&stack, "giteacache")
+                .namespace(namespace)
+                .volume_claim(redisdata.claim().clone().unwrap())
+                .password(password)
+                .memory_limit("256Mi")
+                .monitoring(monitoring.clone())
+                .build();
+            network.endpoint("giteacache", redis.endpoint());
+            redis.cache()
+        }
+        CacheKind::Memory => Cache::Memory,
+    };
+    let mut giteadb = Postgres::create(&stack, "giteadb");
+    giteadb
         .namespace(namespace)
-        .volume_claim(pgdata.claim().clone().unwrap())
         .db_name("gitea")
         .user("gitea")
         .password("gitea")
-        .build();
+        .monitoring(monitoring.clone());
+    // Replicated clusters are initialized empty, so existing databases are not switched.
+    let giteadb_storage = match config.database {
+        Some(_) => "replicated",
+        None => "standalone",
+    };
+    check_storage(
+        &previous_outputs,
+        "giteadb_storage",
+        "standalone",
+        giteadb_storage,
+    )
+    .context(
+        "database: can not migrate the Gitea database of an existing deployment, back up the \
+        database using `pg_dump` and restore it into a new deployment instead",
+    )?;
+    outputs.add("giteadb_storage", giteadb_storage);
+    match &config.database {
+        Some(database) => {
+            let superuser_password = generated_secret("postgres_superuser_password")?;
+            let replication_password = generated_secret("postgres_replication_password")?;
+            outputs.add_sensitive("postgres_superuser_password", &superuser_password);
+            outputs.add_sensitive("postgres_replication_password", &replication_password);
+            giteadb.replication(PostgresReplication {
+                replicas: database.replicas,
+                storage_class: database.storage_class.clone(),
+                storage: database.size.clone(),
+                superuser_password,
+                replication_password,
+            })
+        }
+        None => {
+            let pgdata_volume = LocalDirVolume::create(&stack, "gitserver-pgdata")
+                .namespace(privileged_namespace)
+                .storage("10Gi")
+                .storage_class(&local_storage_class.metadata[0].name)
+                .mount_path("/mnt/gitea-pgdata")
+                .owner((70, 70))
+                .node(&config.server.node)
+                .build();
+            let pgdata = pgdata_volume.claim("pgdata").namespace(namespace).build();
+            let claim = pgdata.claim().clone().unwrap();
+            giteadb.volume_claim(claim)
+        }
+    };
+    if let Some(pooler) = &config.pooler {
+        giteadb.pooler(PostgresPooler {
+            pool_mode: pooler.mode,
+            pool_size: pooler.pool_size,
+            max_client_conn: pooler.max_client_conn,
+            replicas: pooler.replicas,
+        });
+    }
+    let giteadb = giteadb.build();
+    network.endpoint("giteadb", giteadb.endpoint());
+    // Gitea connects using the pooler if deployed. Other clients (see `network.allow`) may choose
+    // either endpoint.
+    let giteadb_backend = match giteadb.pooler_endpoint() {
+        Some(endpoint) => {
+            network.endpoint("giteadb-pooler", endpoint);
+            "giteadb-pooler"
+        }
+        None => "giteadb",
+    };
     let gitea = Gitea::create(&stack, "gitea")
         .namespace(namespace)
         .domain(&config.server.domain)
         .path("/git")
-        .cache_host("giteacache.gitserver:11211")
-        .db_host("postgres-giteadb.gitserver:5432")
+        .cache(cache)
+        .db_host(giteadb.pooled_host().unwrap_or_else(|| giteadb.host()))
         .db_name("gitea")
         .db_user("gitea")
         .db_password("gitea")
         .root_user(&config.root.user)
         .root_passwd(&config.root.passwd)
         .root_email(&config.root.email)
-        .volume_claim(giteadata.claim().clone().unwrap())
+        .volume_claim(giteadata)
+        .replicas(config.ha.as_ref().map_or(1, |ha| ha.replicas))
+        .shared_volume(config.ha.is_some())
+        .app_ini(config.gitea.app_ini())
+        .mail(mail.clone())
+        .auth_sources(auth_sources)
+        .object_storage(object_storage.unwrap_or_default())
+        .indexer(indexer)
+        .packages(config.packages.is_some())
+        .actions(config.actions.is_some())
+        .monitoring(monitoring.clone())
+        .metrics_token(gitea_metrics_token)
         .build();
+    network.endpoint("gitea", gitea.endpoint());
+    for backend in [
+        giteadb_backend,
+        "giteacache",
+        "giteasearch",
+        "minio",
+        "mailpit",
+    ] {
+        if network.contains(backend) {
+            network.allow(backend, gitea.peer());
+        }
+    }
 
-    let jenkins = Jenkins::create(&stack, "jenkins")
-        .namespace(namespace)
-        .domain(&config.server.domain)
-        .path("/ci")
+    // Replaces the bootstrap job on every apply, so changes made by hand are reverted.
+    let run_id = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string();
+    let bootstrap = gitea
+        .bootstrap("gitea-bootstrap")
+        .state(tf_bindgen::json::to_string(&config.gitea).expect("serializable gitea config"))
+        .mirror_credentials(config.gitea.mirror_credentials())
+        .run_id(run_id)
         .build();
+    network.allow("gitea", bootstrap.peer());
+    outputs.add("gitea_bootstrap_job", "gitea-bootstrap");
 
+    if let Some(actions) = &config.actions {
+        let (executor, runner_namespace) = match actions.executor {
+            config::RunnerExecutor::Dind => (RunnerExecutor::Dind, privileged_namespace),
+            config::RunnerExecutor::Host => (RunnerExecutor::Host, namespace),
+        };
+        let runner = gitea
+            .runner("gitea-runner")
+            .namespace(runner_namespace)
+            .replicas(actions.runners)
+            .capacity(actions.capacity)
+            .labels(actions.labels.clone())
+            .executor(executor)
+            .storage_class(actions.storage_class.clone())
+            .storage(&actions.size)
+            .build();
+        network.allow("gitea", runner.peer());
+    }
+
+    let ci: Option<Rc<dyn CiServer>> = match config.ci.kind {
+        CiKind::Jenkins => {
+            let jenkins = Jenkins::create(&stack, "jenkins")
+                .namespace(namespace)
+                .domain(&config.server.domain)
+                .path("/ci")
+                .mail(mail.clone())
+                .sso(jenkins_sso)
+                .monitoring(monitoring.clone())
+                .build();
+            network.endpoint("jenkins", jenkins.endpoint());
+            network.allow("jenkins", gitea.peer());
+            if network.contains("mailpit") {
+                network.allow("mailpit", jenkins.peer());
+            }
+            Some(jenkins)
+        }
+        CiKind::Woodpecker => {
+            let woodpeckerdata_volume = LocalDirVolume::create(&stack, "gitserver-woodpeckerdata")
+                .namespace(privileged_namespace)
+                .storage("1Gi")
+                .storage_class(&local_storage_class.metadata[0].name)
+                .mount_path("/mnt/woodpecker-pgdata")
+                .owner((70, 70))
+                .node(&config.server.node)
+                .build();
+            let woodpeckerdata = woodpeckerdata_volume
+                .claim("woodpeckerdata")
+                .namespace(namespace)
+                .build();
+            let woodpeckerdb = Postgres::create(&stack, "woodpeckerdb")
+                .namespace(namespace)
+                .volume_claim(woodpeckerdata.claim().clone().unwrap())
+                .db_name("woodpecker")
+                .user("woodpecker")
+                .password("woodpecker")
+                .monitoring(monitoring.clone())
+                .build();
+            let agent_secret = config.ci.agent_secret.clone();
+            let woodpecker = gitea
+                .woodpecker("woodpecker")
+                .path("/ci")
+                .agent_namespace(privileged_namespace)
+                .agent_secret(agent_secret.expect("validated agent secret"))
+                .agents(config.ci.agents)
+                .db_host(woodpeckerdb.host())
+                .db_name("woodpecker")
+                .db_user("woodpecker")
+                .db_password("woodpecker")
+                .monitoring(monitoring.clone())
+                .metrics_token(woodpecker_metrics_token)
+                .build();
+            network.endpoint("woodpeckerdb", woodpeckerdb.endpoint());
+            network.endpoint("woodpecker", woodpecker.endpoint());
+            network.allow("woodpeckerdb", woodpecker.peer());
+            Some(woodpecker)
+        }
+        CiKind::None => None,
+    };
+    if let Some(ci) = &ci {
+        network.allow("gitea", ci.peer());
+    }
+
+    if let Some(logs) = &config.logs {
+        let sink = match logs.sink {
+            LogSinkKind::File => LogSink::File {
+                path: logs.path.clone(),
+                max_size: logs.max_size,
+                retention_days: logs.retention_days,
+            },
+            LogSinkKind::Loki => {
+                let url = logs.url.as_deref().expect("validated loki url");
+                LogSink::Loki(Url::parse(url).expect("validated loki url"))
+            }
+            LogSinkKind::Syslog => LogSink::Syslog {
+                host: logs.host.clone().expect("validated syslog host"),
+                port: logs.port,
+                tcp: logs.protocol == SyslogProtocol::Tcp,
+            },
+        };
+        LogCollector::create(&stack, "logs")
+            .namespace(privileged_namespace)
+            .source_namespace(namespace)
+            .sink(sink)
+            .monitoring(monitoring.clone())
+            .build();
+    }
+
+    if let (Some(monitoring), Some(scrape_jobs)) = (&config.monitoring, &scrape_jobs) {
+        let alertmanager = Alertmanager::create(&stack, "alertmanager")
+            .namespace(namespace)
+            .mail(mail)
+            .receiver(monitoring.alert_email.clone())
+            .build();
+        network.endpoint("alertmanager", alertmanager.endpoint());
+        if network.contains("mailpit") {
+            network.allow("mailpit", alertmanager.peer());
+        }
+        let prometheusdata_volume = LocalDirVolume::create(&stack, "gitserver-prometheusdata")
+            .namespace(privileged_namespace)
+            .storage("10Gi")
+            .storage_class(&local_storage_class.metadata[0].name)
+            .mount_path("/mnt/prometheus-data")
+            .owner((65534, 65534))
+            .node(&config.server.node)
+            .build();
+        let prometheusdata = prometheusdata_volume
+            .claim("prometheusdata")
+            .namespace(namespace)
+            .build();
+        let prometheus = Prometheus::create(&stack, "prometheus")
+            .namespace(namespace)
+            .volume_claim(prometheusdata.claim().clone().unwrap())
+            .scrape_jobs(scrape_jobs.take())
+            .alertmanager(alertmanager.address())
+            .build();
+        network.endpoint("prometheus", prometheus.endpoint());
+        network.allow("alertmanager", prometheus.peer());
+        let grafanadata_volume = LocalDirVolume::create(&stack, "gitserver-grafanadata")
+            .namespace(privileged_namespace)
+            .storage("1Gi")
+            .storage_class(&local_storage_class.metadata[0].name)
+            .mount_path("/mnt/grafana-data")
+            .owner((472, 472))
+            .node(&config.server.node)
+            .build();
+        let grafanadata = grafanadata_volume
+            .claim("grafanadata")
+            .namespace(namespace)
+            .build();
+        let admin_password = generated_secret("grafana_admin_password")?;
+        outputs.add_sensitive("grafana_admin_password", &admin_password);
+        let grafana = gitea
+            .grafana("grafana")
+            .path("/grafana")
+            .volume_claim(grafanadata.claim().clone().unwrap())
+            .admin_password(admin_password)
+            .prometheus_url(prometheus.url())
+            .alertmanager_url(alertmanager.url())
+            .build();
+        network.endpoint("grafana", grafana.endpoint());
+        for backend in ["gitea", "prometheus", "alertmanager"] {
+            network.allow(backend, grafana.peer());
+        }
+        ingress_services.push(grafana.ingress());
+        outputs.add("grafana_url", grafana.url());
+    }
+
+    ingress_services.push(gitea.ingress());
+    if let Some(ci) = &ci {
+        ingress_services.push(ci.ingress());
+    }
+    let ingress_controller = NetworkPeer::Namespace {
+        name: config.network.ingress_namespace.clone(),
+        labels: config.network.ingress_labels.clone(),
+    };
     Ingress::create(&stack, "gitserver")
         .namespace(namespace)
-        .services(vec![gitea.ingress(), jenkins.ingress()])
+        .services(ingress_services)
+        .controller(ingress_controller.clone())
         .build();
+    // Uploads of packages may be large and slow, so the limits of the ingress controller are only
+    // lifted for the package registries.
+    if let Some(packages) = &config.packages {
+        Ingress::create(&stack, "gitserver-packages")
+            .namespace(namespace)
+            .services(gitea.packages_ingress())
+            .annotations(packages.ingress_annotations())
+            .controller(ingress_controller)
+            .build();
+    }
+
+    for allow in &config.network.allow {
+        if !network.contains(&allow.component) {
+            bail!(
+                "network.allow: component '{}' is not deployed, expected one of: {}",
+                allow.component,
+                network.components().join(", ")
+            )
+        }
+        network.allow(&allow.component, network_peer(allow));
+    }
+    network.build(&stack, namespace);
+
+    outputs
+        .add("gitea_url", gitea.url())
+        .add("gitea_ssh_url", gitea.ssh_url())
+        .add("admin_credentials", gitea.credentials());
+    if let Some(ci) = &ci {
+        outputs.add("ci_kind", ci.kind()).add("ci_url", ci.url());
+    }
+
+    Ok((stack, outputs))
+}
 
-    stack
+/// Returns the OAuth2 clients of Gitea and, if used as CI server, Jenkins. Their callback URLs
+/// must match the paths used in [`init`].
+fn sso_clients(domain: &str, sso: &Sso, ci: CiKind) -> Vec<DexClient> {
+    let mut clients = vec![DexClient {
+        id: "gitea".to_string(),
+        name: "Gitea".to_string(),
+        secret: sso.gitea_client_secret.clone(),
+        redirect_uris: vec![format!(
+            "https://{domain}/git/user/oauth2/{SSO_SOURCE}/callback"
+        )],
+    }];
+    if let (CiKind::Jenkins, Some(secret)) = (ci, &sso.jenkins_client_secret) {
+        clients.push(DexClient {
+            id: "jenkins".to_string(),
+            name: "Jenkins".to_string(),
+            secret: secret.clone(),
+            redirect_uris: vec![format!("https://{domain}/ci/securityRealm/finishLogin")],
+        });
+    }
+    clients
+}
+
+fn network_peer(allow: &NetworkAllow) -> NetworkPeer {
+    match (&allow.cidr, &allow.namespace) {
+        (Some(cidr), _) => NetworkPeer::IpBlock(cidr.clone()),
+        (None, Some(namespace)) => NetworkPeer::Namespace {
+            name: namespace.clone(),
+            labels: allow.pod_labels.clone(),
+        },
+        (None, None) => NetworkPeer::Pods(allow.pod_labels.clone()),
+    }
+}
+
+fn mail_config(mail: &Mail) -> MailConfig {
+    MailConfig {
+        host: mail.host.clone().expect("validated mail host"),
+        port: mail.port,
+        tls: match mail.tls {
+            config::MailTls::None => MailTls::None,
+            config::MailTls::StartTls => MailTls::StartTls,
+            config::MailTls::Tls => MailTls::Tls,
+        },
+        from: mail.from.clone(),
+        user: mail.user.clone(),
+        password: mail.passwd.clone(),
+    }
+}
+
+/// Fails if output `name` of an existing deployment differs from `storage`, since switching the
+/// storage does not migrate the data. Uses `default` if the deployment has no such output.
+fn check_storage(
+    previous_outputs: &HashMap<String, tf_bindgen::json::Value>,
+    name: &str,
+    default: &str,
+    storage: &str,
+) -> anyhow::Result<()> {
+    if !previous_outputs.contains_key("namespace") {
+        return Ok(());
+    }
+    let previous = previous_outputs
+        .get(name)
+        .and_then(|value| value.as_str())
+        .unwrap_or(default);
+    if previous != storage {
+        bail!("the deployment uses {previous} storage, but {storage} storage is configured")
+    }
+    Ok(())
+}
+
+/// Optionally waits for all workloads to become ready and prints the access information of an
+/// applied stack.
+fn post_apply(stack: &Stack, args: &ApplyArgs) -> anyhow::Result<()> {
+    let outputs = Outputs::read(stack)?;
+    let output = |name: &str| {
+        outputs
+            .get(name)
+            .and_then(|value| value.as_str())
+            .unwrap_or("-")
+            .to_string()
+    };
+    // The bootstrap job runs on every apply. Drift corrected or errors raised by it must be
+    // visible to the caller.
+    let namespace = Kubectl::new(output("namespace"));
+    namespace.wait_for_job(
+        &output("gitea_bootstrap_job"),
+        Duration::from_secs(args.timeout),
+    )?;
+    if args.wait {
+        let deadline = Instant::now() + Duration::from_secs(args.timeout);
+        for kubectl in Kubectl::namespaces(&outputs)? {
+            kubectl.wait_for_rollout(deadline.saturating_duration_since(Instant::now()))?;
+        }
+    }
+    println!();
+    println!("Gitea:             {}", output("gitea_url"));
+    println!("Gitea (SSH):       {}", output("gitea_ssh_url"));
+    if outputs.contains_key("ci_url") {
+        let ci = format!("{}:", output("ci_kind"));
+        println!("{ci:<18} {}", output("ci_url"));
+    }
+    println!("Admin credentials: {}", output("admin_credentials"));
+    Ok(())
 }
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
     let config = Config::from_file("gitserver.toml")?;
+    terraform::export_backend_credentials(&config.state);
 
-    let stack = init(config);
+    let (stack, outputs) = init(&config)?;
     let mut command = match cli.command() {
         Command::Init => Terraform::init(&stack)?,
-        Command::Apply => Terraform::apply(&stack)?,
+        Command::Apply(_) => Terraform::apply(&stack)?,
         Command::Destroy => Terraform::destroy(&stack)?,
-    };
-    #[cfg(unix)]
-    {
-        let terminate = Arc::new(AtomicBool::new(false));
-        signal_hook::consts::TERM_SIGNALS
-            .iter()
-            .map(|signal| (signal, Arc::clone(&terminate)))
-            .for_each(|(signal, hook)| {
-                signal_hook::flag::register(*signal, hook).expect("register signal");
-            });
-        let mut child = command.spawn()?;
-        while !terminate.load(Ordering::Relaxed) {
-            if let Some(status) = child.try_wait()? {
-                std::process::exit(status.code().unwrap_or(0));
+        Command::State(StateCommand::Migrate) => {
+            let mut command = Terraform::init(&stack)?;
+            command.arg("-migrate-state");
+            command
+        }
+        Command::Status(args) => {
+            let status = Status::collect(&stack)?;
+            match args.json {
+                true => println!("{}", status.to_json()?),
+                false => status.print(),
             }
-            std::thread::sleep(Duration::from_millis(200));
+            std::process::exit(if status.healthy { 0 } else { 1 });
         }
-        let _ = child.kill();
-    }
-    #[cfg(not(unix))]
-    {
-        todo!()
+        Command::Import(args) => std::process::exit(import::run(&stack, args)?),
+        Command::Logs(args) => std::process::exit(component::logs(&stack, args)?),
+        Command::Exec(args) => std::process::exit(component::exec(&stack, args)?),
+    };
+    terraform::write_backend(&stack, &config.state)?;
+    outputs.write(&stack)?;
+    let code = process::run(&mut command)?;
+    if let (0, Command::Apply(args)) = (code, cli.command()) {
+        post_apply(&stack, args)?;
     }
-    Ok(())
+    std::process::exit(code)
 }
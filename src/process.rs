@@ -0,0 +1,124 @@
+use std::process::{Child, Command, ExitStatus};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use signal_hook::consts::TERM_SIGNALS;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Time given to the child process to shut down gracefully after receiving a termination signal.
+/// The child will be killed afterwards.
+const KILL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Runs `command` until it exits and returns its exit code.
+///
+/// Termination signals received by this process are forwarded to the child process, so Terraform
+/// can release its state lock and stop gracefully. The child will only be killed if it did not
+/// exit within [`KILL_TIMEOUT`].
+///
+/// # Errors
+///
+/// Will return `Err` if failed to register signal handlers or to spawn the child process.
+pub fn run(command: &mut Command) -> Result<i32> {
+    let received = Arc::new(AtomicUsize::new(0));
+    let sent = Arc::new(AtomicBool::new(false));
+    register(&received, &sent)?;
+    let mut child = command.spawn().context("failed to spawn command")?;
+    let mut interrupted_at = None;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(exit_code(status));
+        }
+        let signal = received.swap(0, Ordering::Relaxed);
+        if signal != 0 {
+            eprintln!("received signal {signal}, waiting for child process to stop");
+            // Signals generated by the terminal (e.g. Ctrl+C) are delivered to the whole
+            // foreground process group, so the child process already received them. Forwarding
+            // them again would make Terraform exit immediately.
+            if sent.swap(false, Ordering::Relaxed) {
+                forward(&child, signal as i32);
+            }
+            interrupted_at.get_or_insert_with(Instant::now);
+        }
+        if let Some(interrupted_at) = interrupted_at {
+            if interrupted_at.elapsed() > KILL_TIMEOUT {
+                eprintln!("child process did not stop in time, killing it");
+                let _ = child.kill();
+                return Ok(exit_code(child.wait()?));
+            }
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Registers handlers storing the received termination signal in `received`. Sets `sent` if the
+/// signal was sent by another process (e.g. using `kill`) instead of the terminal.
+#[cfg(unix)]
+fn register(received: &Arc<AtomicUsize>, sent: &Arc<AtomicBool>) -> Result<()> {
+    for signal in TERM_SIGNALS {
+        let received = Arc::clone(received);
+        let sent = Arc::clone(sent);
+        let action = move |info: &libc::siginfo_t| {
+            if sent_by_process(info) {
+                sent.store(true, Ordering::Relaxed);
+            }
+            received.store(info.si_signo as usize, Ordering::Relaxed);
+        };
+        // SAFETY: The action only stores atomics, which is async-signal-safe.
+        unsafe { signal_hook_registry::register_sigaction(*signal, action) }
+            .context("failed to register signal handler")?;
+    }
+    Ok(())
+}
+
+/// Returns `true` if the signal was sent using `kill`, `sigqueue` or `tgkill`. Like `SI_FROMUSER`,
+/// these use non-positive codes on Linux.
+#[cfg(target_os = "linux")]
+fn sent_by_process(info: &libc::siginfo_t) -> bool {
+    info.si_code <= 0
+}
+
+/// Returns `true` if the signal was sent using `kill` (`SI_USER` on macOS and BSD).
+#[cfg(all(unix, not(target_os = "linux")))]
+fn sent_by_process(info: &libc::siginfo_t) -> bool {
+    info.si_code == 0x10001
+}
+
+#[cfg(not(unix))]
+fn register(received: &Arc<AtomicUsize>, _: &Arc<AtomicBool>) -> Result<()> {
+    // Console control events are delivered to all processes attached to the console, so the
+    // child process already received the signal.
+    for signal in TERM_SIGNALS {
+        signal_hook::flag::register_usize(*signal, Arc::clone(received), *signal as usize)
+            .context("failed to register signal handler")?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn forward(child: &Child, signal: i32) {
+    // SAFETY: `kill` has no memory safety requirements. The PID belongs to our child process,
+    // which was not reaped yet.
+    unsafe {
+        libc::kill(child.id() as libc::pid_t, signal);
+    }
+}
+
+#[cfg(not(unix))]
+fn forward(_: &Child, _: i32) {}
+
+#[cfg(unix)]
+fn exit_code(status: ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+    status
+        .code()
+        .or_else(|| status.signal().map(|signal| 128 + signal))
+        .unwrap_or(1)
+}
+
+#[cfg(not(unix))]
+fn exit_code(status: ExitStatus) -> i32 {
+    status.code().unwrap_or(1)
+}
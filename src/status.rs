@@ -0,0 +1,254 @@
+use anyhow::{Context, Result};
+use tf_bindgen::json::{self, Value};
+use tf_bindgen::serde::Serialize;
+use tf_bindgen::Stack;
+
+use crate::kubectl::Kubectl;
+use crate::terraform::Outputs;
+
+#[derive(Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct Status {
+    pub healthy: bool,
+    pub workloads: Vec<Check>,
+    pub jobs: Vec<Check>,
+    pub pods: Vec<Check>,
+    pub volume_claims: Vec<Check>,
+    pub ingresses: Vec<Check>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "::tf_bindgen::serde")]
+pub struct Check {
+    pub kind: String,
+    pub name: String,
+    pub healthy: bool,
+    pub message: String,
+}
+
+impl Status {
+    /// Collect the status of all components deployed by `stack`. Requires the stack to be applied
+    /// so that the namespaces can be read from the Terraform outputs.
+    pub fn collect(stack: &Stack) -> Result<Self> {
+        let outputs = Outputs::read(stack)?;
+        let mut workloads = Vec::new();
+        let mut jobs = Vec::new();
+        let mut pods = Vec::new();
+        let mut volume_claims = Vec::new();
+        let mut ingresses = Vec::new();
+        for kubectl in Kubectl::namespaces(&outputs)? {
+            for kind in ["statefulsets", "deployments"] {
+                workloads.extend(kubectl.get(kind)?.iter().map(workload_check));
+            }
+            workloads.extend(kubectl.get("daemonsets")?.iter().map(daemon_set_check));
+            jobs.extend(kubectl.get("jobs")?.iter().map(job_check));
+            let unhealthy_events =
+                kubectl.get_with("events", &["--field-selector=reason=Unhealthy"])?;
+            pods.extend(
+                kubectl
+                    .get("pods")?
+                    .iter()
+                    .map(|pod| pod_check(pod, &unhealthy_events)),
+            );
+            volume_claims.extend(
+                kubectl
+                    .get("persistentvolumeclaims")?
+                    .iter()
+                    .map(volume_claim_check),
+            );
+            ingresses.extend(kubectl.get("ingresses")?.iter().map(ingress_check));
+        }
+
+        let mut status = Self {
+            healthy: true,
+            workloads,
+            jobs,
+            pods,
+            volume_claims,
+            ingresses,
+        };
+        let healthy = status.checks().all(|check| check.healthy);
+        status.healthy = healthy;
+        Ok(status)
+    }
+
+    fn checks(&self) -> impl Iterator<Item = &Check> {
+        self.workloads
+            .iter()
+            .chain(&self.jobs)
+            .chain(&self.pods)
+            .chain(&self.volume_claims)
+            .chain(&self.ingresses)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        json::to_string_pretty(self).context("failed to serialize status")
+    }
+
+    pub fn print(&self) {
+        for check in self.checks() {
+            let state = match check.healthy {
+                true => "ok",
+                false => "FAIL",
+            };
+            println!(
+                "{state:<4}  {:<22}  {:<28}  {}",
+                check.kind, check.name, check.message
+            );
+        }
+        match self.healthy {
+            true => println!("\nall components are healthy"),
+            false => println!("\nsome components are unhealthy"),
+        }
+    }
+}
+
+fn name_of(object: &Value) -> String {
+    object["metadata"]["name"]
+        .as_str()
+        .unwrap_or("<unknown>")
+        .to_string()
+}
+
+fn workload_check(workload: &Value) -> Check {
+    let desired = workload["spec"]["replicas"].as_i64().unwrap_or(1);
+    let ready = workload["status"]["readyReplicas"].as_i64().unwrap_or(0);
+    Check {
+        kind: workload["kind"].as_str().unwrap_or("Workload").to_string(),
+        name: name_of(workload),
+        healthy: ready >= desired,
+        message: format!("{ready}/{desired} replicas ready"),
+    }
+}
+
+fn daemon_set_check(daemon_set: &Value) -> Check {
+    let desired = daemon_set["status"]["desiredNumberScheduled"]
+        .as_i64()
+        .unwrap_or(0);
+    let ready = daemon_set["status"]["numberReady"].as_i64().unwrap_or(0);
+    Check {
+        kind: "DaemonSet".to_string(),
+        name: name_of(daemon_set),
+        healthy: ready >= desired,
+        message: format!("{ready}/{desired} pods ready"),
+    }
+}
+
+fn job_check(job: &Value) -> Check {
+    let condition = |kind: &str| {
+        job["status"]["conditions"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|condition| condition["type"] == kind && condition["status"] == "True")
+    };
+    let (healthy, message) = match (condition("Complete"), condition("Failed")) {
+        (Some(_), _) => (true, "complete".to_string()),
+        (None, Some(failed)) => (
+            false,
+            format!(
+                "failed: {}",
+                failed["message"].as_str().unwrap_or("unknown reason")
+            ),
+        ),
+        (None, None) => (true, "running".to_string()),
+    };
+    Check {
+        kind: "Job".to_string(),
+        name: name_of(job),
+        healthy,
+        message,
+    }
+}
+
+fn pod_check(pod: &Value, unhealthy_events: &[Value]) -> Check {
+    let name = name_of(pod);
+    let phase = pod["status"]["phase"].as_str().unwrap_or("Unknown");
+    if phase == "Succeeded" {
+        return Check {
+            kind: "Pod".to_string(),
+            name,
+            healthy: true,
+            message: phase.to_string(),
+        };
+    }
+    let mut problems = Vec::new();
+    let init_containers = pod["status"]["initContainerStatuses"].as_array();
+    for container in init_containers.into_iter().flatten() {
+        let container_name = container["name"].as_str().unwrap_or_default();
+        let terminated = &container["state"]["terminated"];
+        let waiting = &container["state"]["waiting"];
+        let last_terminated = &container["lastState"]["terminated"];
+        if terminated["exitCode"].as_i64().unwrap_or(0) != 0 {
+            problems.push(format!(
+                "init container '{container_name}' failed with exit code {}",
+                terminated["exitCode"]
+            ));
+        } else if last_terminated["exitCode"].as_i64().unwrap_or(0) != 0 {
+            problems.push(format!(
+                "init container '{container_name}' failed with exit code {} ({})",
+                last_terminated["exitCode"],
+                waiting["reason"].as_str().unwrap_or("restarting")
+            ));
+        }
+    }
+    let containers = pod["status"]["containerStatuses"].as_array();
+    for container in containers.into_iter().flatten() {
+        let container_name = container["name"].as_str().unwrap_or_default();
+        if !container["ready"].as_bool().unwrap_or(false) {
+            let reason = container["state"]["waiting"]["reason"]
+                .as_str()
+                .unwrap_or("not ready");
+            problems.push(format!("container '{container_name}': {reason}"));
+        }
+    }
+    let probe_failures = unhealthy_events
+        .iter()
+        .filter(|event| event["involvedObject"]["name"].as_str() == Some(&name))
+        .filter_map(|event| event["message"].as_str())
+        .next_back();
+    let healthy = problems.is_empty() && phase == "Running";
+    if let (false, Some(message)) = (healthy, probe_failures) {
+        problems.push(format!("probe: {message}"));
+    }
+    Check {
+        kind: "Pod".to_string(),
+        name,
+        healthy,
+        message: match problems.is_empty() {
+            true => phase.to_string(),
+            false => format!("{phase}: {}", problems.join("; ")),
+        },
+    }
+}
+
+fn volume_claim_check(claim: &Value) -> Check {
+    let phase = claim["status"]["phase"].as_str().unwrap_or("Unknown");
+    let volume = claim["spec"]["volumeName"].as_str().unwrap_or("-");
+    Check {
+        kind: "PersistentVolumeClaim".to_string(),
+        name: name_of(claim),
+        healthy: phase == "Bound",
+        message: format!("{phase} (volume: {volume})"),
+    }
+}
+
+fn ingress_check(ingress: &Value) -> Check {
+    let addresses: Vec<_> = ingress["status"]["loadBalancer"]["ingress"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|address| address["ip"].as_str().or(address["hostname"].as_str()))
+        .collect();
+    // Ingress controllers exposed using a node port or the host network (e.g. on bare metal) do
+    // not publish an address, so a missing address is not a failure.
+    Check {
+        kind: "Ingress".to_string(),
+        name: name_of(ingress),
+        healthy: true,
+        message: match addresses.is_empty() {
+            true => "no load balancer address (expected for node ports)".to_string(),
+            false => addresses.join(", "),
+        },
+    }
+}
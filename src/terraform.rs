@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use tf_bindgen::json;
+use tf_bindgen::value::IntoValue;
+use tf_bindgen::{Stack, Value};
+
+use crate::config::State;
+use crate::kubectl::{self, KUBE_CONFIG};
+
+/// Directory used by [`tf_bindgen::cli::Terraform`] to store synthesized stacks.
+const PATH: &str = "target/stacks";
+
+/// Returns the directory containing the synthesized Terraform configuration of `stack`.
+pub fn stack_dir(stack: &Stack) -> String {
+    format!("{PATH}/{}", stack.name())
+}
+
+/// Returns a prepared Terraform command running inside the stack directory.
+pub fn command(stack: &Stack) -> Command {
+    let mut command = Command::new("terraform");
+    command.arg(format!("-chdir={}", stack_dir(stack)));
+    command
+}
+
+/// Writes the backend configuration to `backend.tf.json` inside the stack directory. Must be
+/// called after the stack was synthesized. Will remove the file if the default local backend is
+/// used.
+pub fn write_backend(stack: &Stack, state: &State) -> Result<()> {
+    let path = format!("{}/backend.tf.json", stack_dir(stack));
+    let backend = match state {
+        State::Local { path: None } => {
+            if Path::new(&path).exists() {
+                std::fs::remove_file(&path).context("failed to remove backend config")?;
+            }
+            return Ok(());
+        }
+        State::Local {
+            path: Some(state_path),
+        } => json::json!({
+            "local": { "path": state_path }
+        }),
+        State::Kubernetes {
+            secret_suffix,
+            namespace,
+        } => json::json!({
+            "kubernetes": {
+                "secret_suffix": secret_suffix,
+                "namespace": namespace,
+                "config_path": kubectl::expand_home(KUBE_CONFIG)
+            }
+        }),
+        State::S3 {
+            bucket,
+            key,
+            region,
+            endpoint,
+            ..
+        } => {
+            let mut config = json::json!({
+                "bucket": bucket,
+                "key": key,
+                "region": region,
+                "use_lockfile": true
+            });
+            if let Some(endpoint) = endpoint {
+                // Required by MinIO and most other S3 compatible storages.
+                config["endpoints"] = json::json!({ "s3": endpoint });
+                config["use_path_style"] = true.into();
+                config["skip_credentials_validation"] = true.into();
+                config["skip_region_validation"] = true.into();
+                config["skip_requesting_account_id"] = true.into();
+            }
+            json::json!({ "s3": config })
+        }
+        State::Pg { schema_name, .. } => {
+            let mut config = json::json!({});
+            if let Some(schema_name) = schema_name {
+                config["schema_name"] = schema_name.as_str().into();
+            }
+            json::json!({ "pg": config })
+        }
+    };
+    let document = json::json!({ "terraform": { "backend": backend } });
+    std::fs::write(path, json::to_string_pretty(&document)?)
+        .context("failed to write backend config")
+}
+
+/// Passes the credentials of the state backend to Terraform using environment variables, so they
+/// are neither written to `backend.tf.json` nor stored by `terraform init`. Must be called before
+/// running any Terraform command.
+pub fn export_backend_credentials(state: &State) {
+    let credentials = match state {
+        State::S3 {
+            access_key,
+            secret_key,
+            ..
+        } => vec![
+            ("AWS_ACCESS_KEY_ID", access_key),
+            ("AWS_SECRET_ACCESS_KEY", secret_key),
+        ],
+        State::Pg { conn_str, .. } => vec![("PG_CONN_STR", conn_str)],
+        State::Local { .. } | State::Kubernetes { .. } => Vec::new(),
+    };
+    for (name, value) in credentials {
+        if let Some(value) = value {
+            std::env::set_var(name, value);
+        }
+    }
+}
+
+/// Terraform outputs declared on a stack. `tf-bindgen` does not support outputs, so they are
+/// written to a separate `outputs.tf.json` next to the generated stack configuration.
+#[derive(Default)]
+pub struct Outputs {
+    outputs: HashMap<String, (Value<String>, bool)>,
+}
+
+impl Outputs {
+    /// Declare output `name` with `value`.
+    pub fn add(&mut self, name: impl Into<String>, value: impl IntoValue<String>) -> &mut Self {
+        self.outputs
+            .insert(name.into(), (value.into_value(), false));
+        self
+    }
+
+    /// Declare output `name` with `value`. Terraform will hide the value in its logs.
+    pub fn add_sensitive(
+        &mut self,
+        name: impl Into<String>,
+        value: impl IntoValue<String>,
+    ) -> &mut Self {
+        self.outputs.insert(name.into(), (value.into_value(), true));
+        self
+    }
+
+    /// Writes the declared outputs to `outputs.tf.json` inside the stack directory. Must be
+    /// called after the stack was synthesized.
+    pub fn write(&self, stack: &Stack) -> Result<()> {
+        let outputs: json::Map<_, _> = self
+            .outputs
+            .iter()
+            .map(|(name, (value, sensitive))| {
+                let output = json::json!({
+                    "value": value,
+                    "sensitive": sensitive
+                });
+                (name.clone(), output)
+            })
+            .collect();
+        let document = json::json!({ "output": outputs });
+        let path = format!("{}/outputs.tf.json", stack_dir(stack));
+        std::fs::write(path, json::to_string_pretty(&document)?)
+            .context("failed to write terraform outputs")
+    }
+
+    /// Same as [`Outputs::read`], but returns no outputs if the stack directory was not
+    /// initialized yet, i.e. no state can exist.
+    pub fn read_existing(stack: &Stack) -> Result<HashMap<String, json::Value>> {
+        let initialized = Path::new(&stack_dir(stack)).join(".terraform").exists();
+        match initialized {
+            true => Self::read(stack),
+            false => Ok(HashMap::new()),
+        }
+    }
+
+    /// Reads the current output values of an applied stack using `terraform output`.
+    pub fn read(stack: &Stack) -> Result<HashMap<String, json::Value>> {
+        let output = command(stack)
+            .args(["output", "-json"])
+            .output()
+            .context("failed to run terraform")?;
+        if !output.status.success() {
+            bail!(
+                "terraform output failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+        }
+        let outputs: HashMap<String, json::Value> =
+            json::from_slice(&output.stdout).context("failed to parse terraform outputs")?;
+        Ok(outputs
+            .into_iter()
+            .filter_map(|(name, output)| Some((name, output.get("value")?.clone())))
+            .collect())
+    }
+}